@@ -12,28 +12,91 @@ use curl::easy::Easy;
 
 const SOURCE_URL: &str = "https://gitlab.xiph.org/xiph/opus/-/archive/v1.3.1/opus-v1.3.1.zip";
 const SOURCE_DIGEST: &str = "c3060a34a1981d4b9c03fb1e505675c89b9e8b90926504f0d2f511ee725c3d36";
+#[cfg(feature = "bindgen")]
 const BINDINGS_FILENAME: &str = "opus_bindings.rs";
+#[cfg(all(feature = "bindgen", feature = "custom-modes"))]
+const CUSTOM_BINDINGS_FILENAME: &str = "opus_custom_bindings.rs";
+const VENDOR_DIR: &str = "vendor";
+const MIN_SYSTEM_VERSION: &str = "1.3";
+
+/// Cargo features mapped to libopus cmake options.
+const LIBRARY_OPTIONS: &[(&str, &str)] = &[
+    ("fixed-point", "OPUS_FIXED_POINT"),
+    ("float-approx", "OPUS_FLOAT_APPROX"),
+    ("custom-modes", "OPUS_CUSTOM_MODES"),
+    ("assertions", "OPUS_ASSERTIONS"),
+    ("hardening", "OPUS_HARDENING"),
+    ("disable-intrinsics", "OPUS_DISABLE_INTRINSICS"),
+];
 
 fn main() -> Result<(), Box<dyn Error>> {
     let out_dir = env::var("OUT_DIR")?;
     let out_dir = Path::new(&out_dir);
-    let archive_file = out_dir.join(SOURCE_URL.split('/').last().unwrap());
-    let source_dir = out_dir.join("opus_sources");
+    println!("cargo:rerun-if-env-changed=OPUS_SOURCE_DIR");
+    println!("cargo:rerun-if-env-changed=OPUS_UNVERIFIED_SOURCE");
+    println!("cargo:rerun-if-env-changed=OPUS_SOURCE_ARCHIVE");
+    println!("cargo:rerun-if-env-changed=OPUS_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=OPUS_INCLUDE_DIR");
+    println!("cargo:rerun-if-env-changed=OPUS_STATIC");
+
+    let library = if let Some(lib_dir) = env::var_os("OPUS_LIB_DIR") {
+        warn_ignored_options();
+        prebuilt_library(PathBuf::from(lib_dir))?
+    } else if cfg!(feature = "system") {
+        warn_ignored_options();
+        system_library()?
+    } else {
+        let source_dir = match env::var_os("OPUS_SOURCE_DIR") {
+            Some(source_dir) => {
+                let source_dir = PathBuf::from(source_dir);
+                check_source_dir(&source_dir)?;
+                source_dir
+            }
+            None => {
+                let archive_file = locate_archive(out_dir)?;
+                let source_dir = out_dir.join("opus_sources");
+                unpack_archive(&archive_file, &source_dir)?;
+                source_dir
+            }
+        };
+        build_library(&source_dir)?
+    };
 
-    download_sources(&archive_file, SOURCE_URL, SOURCE_DIGEST)?;
-    unpack_archive(&archive_file, &source_dir)?;
-    let lib_path = build_library(&source_dir)?;
-    generate_bindings(out_dir.join("include"), out_dir.join(BINDINGS_FILENAME))?;
-    link_library(lib_path)?;
+    #[cfg(feature = "bindgen")]
+    generate_bindings(&library.include_dir, out_dir.join(BINDINGS_FILENAME))?;
+    #[cfg(all(feature = "bindgen", feature = "custom-modes"))]
+    generate_custom_bindings(&library.include_dir, out_dir.join(CUSTOM_BINDINGS_FILENAME))?;
+    link_library(&library)?;
 
     Ok(())
 }
 
-fn generate_bindings(source_dir: impl AsRef<Path>, out_file: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
-    let source_dir = source_dir.as_ref();
+/// Where to find libopus headers and what to link.
+struct Library {
+    /// Directory containing `opus.h`.
+    #[cfg_attr(not(feature = "bindgen"), allow(dead_code))]
+    include_dir: PathBuf,
+    link_paths: Vec<PathBuf>,
+    libs: Vec<String>,
+    statik: bool,
+    /// Directory of a shared libopus outside of the system paths,
+    /// added to the runtime search path of this crate's tests and examples.
+    rpath: Option<PathBuf>,
+}
+
+/// Regenerates `src/opus_bindings.rs` into `out_file`, only with the `bindgen` feature.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(include_dir: impl AsRef<Path>, out_file: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let include_dir = include_dir.as_ref();
     let out_file = out_file.as_ref();
     let bindings = bindgen::Builder::default()
-        .header(source_dir.join("opus/opus.h").to_str().unwrap())
+        .header(include_dir.join("opus.h").to_str().unwrap())
+        .header(include_dir.join("opus_multistream.h").to_str().unwrap())
+        .header(include_dir.join("opus_projection.h").to_str().unwrap())
+        .whitelist_function("opus_.*")
+        .whitelist_var("OPUS_.*")
+        // The doxygen markup breaks rustdoc links
+        .generate_comments(false)
         .generate()
         .unwrap();
     bindings
@@ -43,17 +106,228 @@ fn generate_bindings(source_dir: impl AsRef<Path>, out_file: impl AsRef<Path>) -
     Ok(())
 }
 
-fn build_library(source_dir: impl AsRef<Path>) -> Result<PathBuf, Box<dyn Error>> {
-    let lib_path = cmake::Config::new(source_dir).build();
-    Ok(lib_path)
+/// Regenerates `src/opus_custom_bindings.rs`, the Opus Custom API on top of
+/// the main bindings, which already define the shared types.
+#[cfg(all(feature = "bindgen", feature = "custom-modes"))]
+fn generate_custom_bindings(
+    include_dir: impl AsRef<Path>,
+    out_file: impl AsRef<Path>,
+) -> Result<(), Box<dyn Error>> {
+    let include_dir = include_dir.as_ref();
+    let bindings = bindgen::Builder::default()
+        .header(include_dir.join("opus_custom.h").to_str().unwrap())
+        // The init functions are only declared for libopus builds with it
+        .clang_arg("-DCUSTOM_MODES")
+        .whitelist_function("opus_custom_.*")
+        .blacklist_type("opus_int(16|32)")
+        .generate_comments(false)
+        .generate()
+        .unwrap();
+    bindings.write_to_file(out_file).unwrap();
+
+    Ok(())
+}
+
+fn has_feature(feature: &str) -> bool {
+    let name = feature.to_uppercase().replace('-', "_");
+    env::var_os(format!("CARGO_FEATURE_{}", name)).is_some()
 }
 
-fn link_library(lib_path: impl AsRef<Path>)->Result<(), Box<dyn Error>>{
-    println!("cargo:rustc-link-search=native={}/lib", lib_path.as_ref().display());
-    println!("cargo:rustc-link-lib=static=opus");
+fn warn_ignored_options() {
+    for (feature, _) in LIBRARY_OPTIONS {
+        if has_feature(feature) {
+            println!(
+                "cargo:warning=feature `{}` has no effect on a prebuilt libopus",
+                feature
+            );
+        }
+    }
+}
+
+fn build_library(source_dir: impl AsRef<Path>) -> Result<Library, Box<dyn Error>> {
+    let dynamic = cfg!(feature = "dynamic");
+    let mut config = cmake::Config::new(source_dir);
+    config.define("BUILD_SHARED_LIBS", if dynamic { "ON" } else { "OFF" });
+    for (feature, option) in LIBRARY_OPTIONS {
+        config.define(option, if has_feature(feature) { "ON" } else { "OFF" });
+    }
+    // 1.3.1 has no cmake switches for these, only the defines
+    if has_feature("hardening") {
+        config.cflag("-DENABLE_HARDENING");
+    }
+    if has_feature("float-approx") {
+        config.cflag("-DFLOAT_APPROX");
+    }
+    if has_feature("assertions") {
+        config.cflag("-DENABLE_ASSERTIONS");
+    }
+    // OPUS_DISABLE_INTRINSICS skips the NEON detection on ARM, but not the
+    // SSE/AVX one on x86, which has its own options
+    if has_feature("disable-intrinsics") {
+        for option in &[
+            "OPUS_X86_MAY_HAVE_SSE",
+            "OPUS_X86_MAY_HAVE_SSE2",
+            "OPUS_X86_MAY_HAVE_SSE4_1",
+            "OPUS_X86_MAY_HAVE_AVX",
+            "OPUS_MAY_HAVE_NEON",
+            "OPUS_PRESUME_NEON",
+        ] {
+            config.define(option, "OFF");
+        }
+    }
+    let lib_path = config.build();
+    Ok(Library {
+        include_dir: lib_path.join("include/opus"),
+        link_paths: vec![lib_path.join("lib")],
+        libs: vec!["opus".to_string()],
+        statik: !dynamic,
+        rpath: if dynamic { Some(lib_path.join("lib")) } else { None },
+    })
+}
+
+fn is_static() -> bool {
+    matches!(env::var_os("OPUS_STATIC"), Some(value) if value != "0")
+}
+
+/// Library found through pkg-config, the bindings are generated against the
+/// 1.3 API so anything older is rejected.
+fn system_library() -> Result<Library, Box<dyn Error>> {
+    let library = pkg_config::Config::new()
+        .atleast_version(MIN_SYSTEM_VERSION)
+        .statik(is_static())
+        .cargo_metadata(false)
+        .probe("opus")?;
+
+    let include_dir = library
+        .include_paths
+        .iter()
+        .flat_map(|path| vec![path.clone(), path.join("opus")])
+        .find(|path| path.join("opus.h").exists())
+        .ok_or("pkg-config found libopus but not opus.h")?;
+
+    Ok(Library {
+        include_dir,
+        link_paths: library.link_paths,
+        libs: library.libs,
+        statik: is_static(),
+        rpath: None,
+    })
+}
+
+/// Library in `OPUS_LIB_DIR`, headers in `OPUS_INCLUDE_DIR` or `../include/opus`.
+/// The version can't be checked here, it's up to the caller.
+fn prebuilt_library(lib_dir: PathBuf) -> Result<Library, Box<dyn Error>> {
+    let include_dir = match env::var_os("OPUS_INCLUDE_DIR") {
+        Some(include_dir) => PathBuf::from(include_dir),
+        None => lib_dir.join("../include/opus"),
+    };
+    if !include_dir.join("opus.h").exists() {
+        return Err(format!(
+            "opus.h not found in {}, set OPUS_INCLUDE_DIR",
+            include_dir.display()
+        )
+        .into());
+    }
+
+    let statik = is_static();
+    Ok(Library {
+        include_dir,
+        link_paths: vec![lib_dir.clone()],
+        libs: vec!["opus".to_string()],
+        statik,
+        rpath: if statik { None } else { Some(lib_dir) },
+    })
+}
+
+fn link_library(library: &Library) -> Result<(), Box<dyn Error>> {
+    for path in &library.link_paths {
+        println!("cargo:rustc-link-search=native={}", path.display());
+    }
+    for lib in &library.libs {
+        if lib == "opus" && library.statik {
+            println!("cargo:rustc-link-lib=static={}", lib);
+        } else {
+            println!("cargo:rustc-link-lib={}", lib);
+        }
+    }
+    if let Some(rpath) = &library.rpath {
+        if env::var("CARGO_CFG_TARGET_FAMILY")? == "unix" {
+            println!("cargo:rustc-link-arg=-Wl,-rpath,{}", rpath.display());
+        }
+    }
     Ok(())
 }
 
+/// A source tree can't be checked against `SOURCE_DIGEST`, so it's only used
+/// with `OPUS_UNVERIFIED_SOURCE=1` set as well.
+fn check_source_dir(source_dir: &Path) -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-changed={}", source_dir.display());
+
+    if !matches!(env::var_os("OPUS_UNVERIFIED_SOURCE"), Some(value) if value != "0") {
+        return Err(format!(
+            "OPUS_SOURCE_DIR={} is not checked against the pinned SHA-256, \
+             set OPUS_UNVERIFIED_SOURCE=1 to build it anyway",
+            source_dir.display()
+        )
+        .into());
+    }
+    println!(
+        "cargo:warning=building libopus from the unverified source tree {}",
+        source_dir.display()
+    );
+
+    for file in &["CMakeLists.txt", "include/opus.h"] {
+        if !source_dir.join(file).exists() {
+            return Err(format!(
+                "OPUS_SOURCE_DIR={} doesn't look like a libopus source tree, {} is missing",
+                source_dir.display(),
+                file
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the libopus source archive, in order: `OPUS_SOURCE_ARCHIVE`,
+/// `vendor/` next to the manifest, download into `out_dir`.
+/// Local archives must match `SOURCE_DIGEST`, nothing is fetched for them.
+fn locate_archive(out_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let archive_name = SOURCE_URL.rsplit('/').next().unwrap();
+    let vendored_file = Path::new(&env::var("CARGO_MANIFEST_DIR")?)
+        .join(VENDOR_DIR)
+        .join(archive_name);
+
+    let local_file = match env::var_os("OPUS_SOURCE_ARCHIVE") {
+        Some(archive_file) => Some(PathBuf::from(archive_file)),
+        None if vendored_file.exists() => Some(vendored_file),
+        None => None,
+    };
+
+    match local_file {
+        Some(archive_file) => {
+            println!("cargo:rerun-if-changed={}", archive_file.display());
+            let hash = calc_hash(&mut File::open(&archive_file)?)?.finalize();
+            if hash[..] != hex::decode(SOURCE_DIGEST)?[..] {
+                return Err(format!(
+                    "{:?} has invalid digest {} vs {}",
+                    archive_file,
+                    SOURCE_DIGEST,
+                    hex::encode(&hash[..])
+                )
+                .into());
+            }
+            Ok(archive_file)
+        }
+        None => {
+            let archive_file = out_dir.join(archive_name);
+            download_sources(&archive_file, SOURCE_URL, SOURCE_DIGEST)?;
+            Ok(archive_file)
+        }
+    }
+}
+
 fn unpack_archive(
     archive_file: impl AsRef<Path>,
     source_dir: impl AsRef<Path>,
@@ -122,7 +396,7 @@ fn download_sources(
 
     if archive_file.exists() {
         let hash = calc_hash(&mut File::open(archive_file)?)?.finalize();
-        if hash.as_slice() == digest.as_slice() {
+        if hash[..] == digest[..] {
             return Ok(());
         }
     }
@@ -132,28 +406,28 @@ fn download_sources(
         .write(true)
         .create(true)
         .truncate(true)
-        .open(&archive_file)?;
+        .open(archive_file)?;
     let hasher = Arc::new(Mutex::new(Sha256::new()));
 
     let mut easy = Easy::new();
     easy.url(url)?;
     let hasher2 = Arc::clone(&hasher);
     easy.write_function(move |data| {
-        hasher2.lock().unwrap().update(&data);
-        fs.write_all(&data).unwrap();
+        hasher2.lock().unwrap().update(data);
+        fs.write_all(data).unwrap();
         Ok(data.len())
     })?;
     easy.perform()?;
 
     let hash = hasher.lock().unwrap().clone().finalize();
-    if digest.as_slice() == hash.as_slice() {
+    if digest[..] == hash[..] {
         Ok(())
     } else {
         panic!(
             "{:?} has invalid digest {} vs {}",
             archive_file,
-            hex::encode(digest.as_slice()),
-            hex::encode(hash.as_slice())
+            hex::encode(&digest[..]),
+            hex::encode(&hash[..])
         )
     }
 }
@@ -167,7 +441,7 @@ fn calc_hash(fs: &mut impl Read) -> Result<Sha256, std::io::Error> {
         if bytes.is_empty() {
             break;
         }
-        hasher.update(&bytes);
+        hasher.update(bytes);
     }
     Ok(hasher)
 }
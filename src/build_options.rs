@@ -0,0 +1,29 @@
+//! Compile-time options libopus was built with.
+//!
+//! Each constant mirrors the cargo feature of the same name. They describe the
+//! library built from source; a system or `OPUS_LIB_DIR` library is used as
+//! is and these only tell what was requested. [`fixed_point`] asks the linked
+//! library instead, so it holds for any of them.
+
+use std::ffi::CStr;
+
+use crate::sys::opus_get_version_string;
+
+/// `fixed-point`: whether the linked libopus uses fixed-point arithmetic
+/// instead of floating point, from the `-fixed` suffix of its version string.
+pub fn fixed_point() -> bool {
+    // A static string owned by libopus.
+    let version = unsafe { CStr::from_ptr(opus_get_version_string()) };
+    version.to_string_lossy().contains("-fixed")
+}
+
+/// `float-approx`: fast floating point approximations.
+pub const FLOAT_APPROX: bool = cfg!(feature = "float-approx");
+/// `custom-modes`: non-Opus sample rates and frame sizes (Opus Custom).
+pub const CUSTOM_MODES: bool = cfg!(feature = "custom-modes");
+/// `assertions`: additional internal consistency checks.
+pub const ASSERTIONS: bool = cfg!(feature = "assertions");
+/// `hardening`: cheap run-time checks suitable for production.
+pub const HARDENING: bool = cfg!(feature = "hardening");
+/// `disable-intrinsics`: no SSE/AVX intrinsics on x86, no NEON on ARM.
+pub const DISABLE_INTRINSICS: bool = cfg!(feature = "disable-intrinsics");
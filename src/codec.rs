@@ -0,0 +1,429 @@
+use std::mem;
+use std::os::raw::{c_int, c_uchar};
+use std::ptr::{self, NonNull};
+
+use crate::config::{ConfigCtls, EncoderConfig};
+use crate::error::{check, Error};
+use crate::packet;
+use crate::params;
+use crate::sample::{self, Dither, Format, Sample, Scratch};
+use crate::sys::*;
+use crate::types::SampleRate;
+
+/// Native encode entry points of an encoder state type.
+pub(crate) trait RawEncoder {
+    unsafe fn encode(
+        st: *mut Self,
+        pcm: *const i16,
+        frame_size: c_int,
+        data: *mut c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> opus_int32;
+
+    unsafe fn encode_float(
+        st: *mut Self,
+        pcm: *const f32,
+        frame_size: c_int,
+        data: *mut c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> opus_int32;
+}
+
+/// Native decode entry points of a decoder state type.
+pub(crate) trait RawDecoder {
+    unsafe fn decode(
+        st: *mut Self,
+        data: *const c_uchar,
+        len: opus_int32,
+        pcm: *mut i16,
+        frame_size: c_int,
+        decode_fec: c_int,
+    ) -> c_int;
+
+    unsafe fn decode_float(
+        st: *mut Self,
+        data: *const c_uchar,
+        len: opus_int32,
+        pcm: *mut f32,
+        frame_size: c_int,
+        decode_fec: c_int,
+    ) -> c_int;
+}
+
+macro_rules! raw_encoder {
+    ($state:ty, $encode:ident, $encode_float:ident) => {
+        impl RawEncoder for $state {
+            unsafe fn encode(
+                st: *mut Self,
+                pcm: *const i16,
+                frame_size: c_int,
+                data: *mut c_uchar,
+                max_data_bytes: opus_int32,
+            ) -> opus_int32 {
+                $encode(st, pcm, frame_size, data, max_data_bytes)
+            }
+
+            unsafe fn encode_float(
+                st: *mut Self,
+                pcm: *const f32,
+                frame_size: c_int,
+                data: *mut c_uchar,
+                max_data_bytes: opus_int32,
+            ) -> opus_int32 {
+                $encode_float(st, pcm, frame_size, data, max_data_bytes)
+            }
+        }
+    };
+}
+
+macro_rules! raw_decoder {
+    ($state:ty, $decode:ident, $decode_float:ident) => {
+        impl RawDecoder for $state {
+            unsafe fn decode(
+                st: *mut Self,
+                data: *const c_uchar,
+                len: opus_int32,
+                pcm: *mut i16,
+                frame_size: c_int,
+                decode_fec: c_int,
+            ) -> c_int {
+                $decode(st, data, len, pcm, frame_size, decode_fec)
+            }
+
+            unsafe fn decode_float(
+                st: *mut Self,
+                data: *const c_uchar,
+                len: opus_int32,
+                pcm: *mut f32,
+                frame_size: c_int,
+                decode_fec: c_int,
+            ) -> c_int {
+                $decode_float(st, data, len, pcm, frame_size, decode_fec)
+            }
+        }
+    };
+}
+
+raw_encoder!(OpusEncoder, opus_encode, opus_encode_float);
+raw_encoder!(
+    OpusMSEncoder,
+    opus_multistream_encode,
+    opus_multistream_encode_float
+);
+raw_encoder!(
+    OpusProjectionEncoder,
+    opus_projection_encode,
+    opus_projection_encode_float
+);
+raw_decoder!(OpusDecoder, opus_decode, opus_decode_float);
+raw_decoder!(
+    OpusMSDecoder,
+    opus_multistream_decode,
+    opus_multistream_decode_float
+);
+raw_decoder!(
+    OpusProjectionDecoder,
+    opus_projection_decode,
+    opus_projection_decode_float
+);
+
+/// Encodes `frame_size` samples per channel of interleaved `pcm` into
+/// `output`, through the native entry point of `T` or converted to float
+/// in `scratch`. Returns the packet length.
+pub(crate) unsafe fn encode<S: RawEncoder, T: Sample>(
+    st: *mut S,
+    pcm: &[T],
+    frame_size: c_int,
+    output: &mut [u8],
+    scratch: &mut Vec<f32>,
+) -> Result<usize, Error> {
+    let max_data_bytes = params::buffer_len(output.len())?;
+    let data = output.as_mut_ptr();
+    let len = match T::FORMAT {
+        // Sound casts, the trait is sealed and only i16 and f32 use these formats.
+        Format::I16 => {
+            let pcm = pcm.as_ptr() as *const i16;
+            S::encode(st, pcm, frame_size, data, max_data_bytes)
+        }
+        Format::F32 => {
+            let pcm = pcm.as_ptr() as *const f32;
+            S::encode_float(st, pcm, frame_size, data, max_data_bytes)
+        }
+        Format::Int(_) => {
+            scratch.clear();
+            scratch.extend(pcm.iter().map(|sample| sample.to_f32()));
+            S::encode_float(st, scratch.as_ptr(), frame_size, data, max_data_bytes)
+        }
+    };
+    Ok(check(len)? as usize)
+}
+
+/// Decodes `frame_size` samples per channel into `pcm`, which holds at least
+/// that many. Formats without a native entry point, and `i16` with dither,
+/// are decoded to float in `scratch` first. An empty `packet` is a lost one.
+#[allow(clippy::too_many_arguments)]
+pub(crate) unsafe fn decode<S: RawDecoder, T: Sample>(
+    st: *mut S,
+    packet: &[u8],
+    pcm: &mut [T],
+    frame_size: usize,
+    channels: usize,
+    fec: bool,
+    dither: &mut Option<Dither>,
+    scratch: &mut Vec<f32>,
+) -> Result<usize, Error> {
+    let data = if packet.is_empty() {
+        ptr::null()
+    } else {
+        packet.as_ptr()
+    };
+    let len = params::packet_len(packet)?;
+    let frame_size = frame_size as c_int;
+    let fec = fec as c_int;
+
+    let samples = match (T::FORMAT, &dither) {
+        // Sound casts, the trait is sealed and only i16 and f32 use these formats.
+        (Format::I16, None) => {
+            let pcm = pcm.as_mut_ptr() as *mut i16;
+            S::decode(st, data, len, pcm, frame_size, fec)
+        }
+        (Format::F32, _) => {
+            let pcm = pcm.as_mut_ptr() as *mut f32;
+            S::decode_float(st, data, len, pcm, frame_size, fec)
+        }
+        _ => {
+            scratch.resize(frame_size as usize * channels, 0.0);
+            let samples = S::decode_float(st, data, len, scratch.as_mut_ptr(), frame_size, fec);
+            if samples > 0 {
+                let len = samples as usize * channels;
+                for (out, &value) in pcm[..len].iter_mut().zip(&scratch[..len]) {
+                    *out = sample::from_f32(value, dither.as_mut());
+                }
+            }
+            samples
+        }
+    };
+    Ok(check(samples)? as usize)
+}
+
+/// Encoding shared by the encoder types: the state, the input layout and the
+/// interleaving buffers. The memory of the state is kept by the owner of the
+/// core.
+#[derive(Debug)]
+pub(crate) struct EncoderCore<S> {
+    state: NonNull<S>,
+    sample_rate: SampleRate,
+    channels: usize,
+    scratch: Scratch,
+}
+
+// The state is a plain block of memory without references to anything else.
+unsafe impl<S> Send for EncoderCore<S> {}
+
+impl<S: RawEncoder + ConfigCtls> EncoderCore<S> {
+    /// Core over the initialized `state`, encoding `channels` at `sample_rate`.
+    pub(crate) fn new(state: NonNull<S>, sample_rate: SampleRate, channels: usize) -> Self {
+        EncoderCore {
+            state,
+            sample_rate,
+            channels,
+            scratch: Scratch::default(),
+        }
+    }
+
+    /// Core with the same settings over `state`, a copy of this one's.
+    pub(crate) fn with_state(&self, state: NonNull<S>) -> Self {
+        EncoderCore::new(state, self.sample_rate, self.channels)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut S {
+        self.state.as_ptr()
+    }
+
+    pub(crate) fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    pub(crate) fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Encodes one frame of interleaved `pcm`, the frame size taken from its
+    /// length.
+    pub(crate) fn encode<T: Sample>(
+        &mut self,
+        pcm: &[T],
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        let frame_size = params::samples_per_channel(pcm.len(), self.channels)?;
+        let frame_size = params::check_frame_size(self.sample_rate, frame_size)?;
+        let st = self.state.as_ptr();
+        unsafe { encode(st, pcm, frame_size, output, &mut self.scratch.f32) }
+    }
+
+    /// Interleaves the planes of `pcm` into the interleaving buffer and
+    /// encodes them.
+    pub(crate) fn planar<T: Sample>(
+        &mut self,
+        pcm: &[&[T]],
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        let lens = pcm.iter().map(|plane| plane.len());
+        let channels = self.channels;
+        let frame_size = params::planar_len(lens, channels)?;
+        let mut scratch = mem::take(&mut self.scratch);
+        let result = if T::FORMAT == Format::I16 {
+            let planes = pcm.iter().filter_map(|plane| sample::as_i16(plane));
+            sample::interleave(planes, channels, frame_size, &mut scratch.i16, |sample| {
+                sample
+            });
+            self.encode(&scratch.i16, output)
+        } else {
+            let planes = pcm.iter().copied();
+            sample::interleave(planes, channels, frame_size, &mut scratch.f32, T::to_f32);
+            self.encode(&scratch.f32, output)
+        };
+        self.scratch = scratch;
+        result
+    }
+
+    /// Validates and applies every setting of `config`.
+    pub(crate) fn apply_config(&mut self, config: &EncoderConfig) -> Result<(), Error> {
+        unsafe { config.apply(self.state.as_ptr()) }
+    }
+
+    /// Effective settings, read back from libopus.
+    pub(crate) fn config(&mut self) -> Result<EncoderConfig, Error> {
+        unsafe { EncoderConfig::read(self.state.as_ptr()) }
+    }
+}
+
+/// What a decoder call does with its packet.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Call {
+    Decode,
+    /// Concealment, or FEC recovery with `fec`.
+    Lost {
+        fec: bool,
+    },
+}
+
+/// Decoding shared by the decoder types: the state, the output layout, the
+/// dither and the interleaving buffers. The memory of the state is kept by
+/// the owner of the core.
+#[derive(Debug)]
+pub(crate) struct DecoderCore<S> {
+    state: NonNull<S>,
+    sample_rate: SampleRate,
+    channels: usize,
+    dither: Option<Dither>,
+    scratch: Scratch,
+}
+
+// The state is a plain block of memory without references to anything else.
+unsafe impl<S> Send for DecoderCore<S> {}
+
+impl<S: RawDecoder> DecoderCore<S> {
+    /// Core over the initialized `state`, decoding `channels` at `sample_rate`.
+    pub(crate) fn new(state: NonNull<S>, sample_rate: SampleRate, channels: usize) -> Self {
+        DecoderCore {
+            state,
+            sample_rate,
+            channels,
+            dither: None,
+            scratch: Scratch::default(),
+        }
+    }
+
+    /// Core with the same settings over `state`, a copy of this one's.
+    pub(crate) fn with_state(&self, state: NonNull<S>) -> Self {
+        DecoderCore {
+            dither: self.dither.clone(),
+            ..DecoderCore::new(state, self.sample_rate, self.channels)
+        }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut S {
+        self.state.as_ptr()
+    }
+
+    pub(crate) fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    pub(crate) fn set_dither(&mut self, enabled: bool) {
+        self.dither = if enabled { Some(Dither::new()) } else { None };
+    }
+
+    /// Runs `call` on interleaved `pcm`. Decoding needs room for the whole
+    /// packet, a lost frame fills all of `pcm`.
+    pub(crate) fn call<T: Sample>(
+        &mut self,
+        packet: &[u8],
+        pcm: &mut [T],
+        call: Call,
+    ) -> Result<usize, Error> {
+        let (frame_size, fec) = match call {
+            Call::Decode => {
+                let frame_size = packet::nb_samples(packet, self.sample_rate)?;
+                if pcm.len() < frame_size * self.channels {
+                    return Err(Error::BufferTooSmall);
+                }
+                (frame_size, false)
+            }
+            Call::Lost { fec } => {
+                let frame_size = params::samples_per_channel(pcm.len(), self.channels)?;
+                (params::check_duration(self.sample_rate, frame_size)?, fec)
+            }
+        };
+        unsafe {
+            decode(
+                self.state.as_ptr(),
+                packet,
+                pcm,
+                frame_size,
+                self.channels,
+                fec,
+                &mut self.dither,
+                &mut self.scratch.f32,
+            )
+        }
+    }
+
+    /// Runs `call` into the interleaving buffer, sized for the planes, and
+    /// splits what was decoded into them.
+    pub(crate) fn planar<T: Sample>(
+        &mut self,
+        packet: &[u8],
+        pcm: &mut [&mut [T]],
+        call: Call,
+    ) -> Result<usize, Error> {
+        let channels = self.channels;
+        let frame_size = params::planar_len(pcm.iter().map(|plane| plane.len()), channels)?;
+        let mut scratch = mem::take(&mut self.scratch);
+        let result = if T::FORMAT == Format::I16 && self.dither.is_none() {
+            scratch.i16.resize(frame_size * channels, 0);
+            let result = self.call(packet, &mut scratch.i16, call);
+            if let Ok(samples) = result {
+                let planes = pcm.iter_mut().filter_map(|plane| sample::as_i16_mut(plane));
+                let decoded = &scratch.i16[..samples * channels];
+                sample::deinterleave(decoded, channels, planes, |sample| sample);
+            }
+            result
+        } else {
+            scratch.f32.resize(frame_size * channels, 0.0);
+            let result = self.call(packet, &mut scratch.f32, call);
+            if let Ok(samples) = result {
+                let planes = pcm.iter_mut().map(|plane| &mut **plane);
+                let decoded = &scratch.f32[..samples * channels];
+                let dither = &mut self.dither;
+                sample::deinterleave(decoded, channels, planes, |value| {
+                    sample::from_f32(value, dither.as_mut())
+                });
+            }
+            result
+        };
+        self.scratch = scratch;
+        result
+    }
+}
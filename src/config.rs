@@ -0,0 +1,432 @@
+use std::convert::TryFrom;
+use std::os::raw::c_int;
+use std::ptr;
+
+use crate::ctl;
+use crate::encoder::Encoder;
+use crate::error::{check, Error};
+use crate::sys::*;
+use crate::types::{Application, Bandwidth, Bitrate, Channels, FrameDuration, SampleRate, Signal};
+
+/// Settings applied to an encoder through its CTLs.
+///
+/// The default is what libopus starts with. `None` stands for `OPUS_AUTO`,
+/// or `OPUS_FRAMESIZE_ARG` for `frame_duration`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderConfig {
+    pub bitrate: Bitrate,
+    pub vbr: bool,
+    pub vbr_constraint: bool,
+    /// 0 to 10.
+    pub complexity: u8,
+    pub max_bandwidth: Bandwidth,
+    pub signal: Option<Signal>,
+    pub inband_fec: bool,
+    /// Expected packet loss, 0 to 100 percent.
+    pub packet_loss_perc: u8,
+    pub dtx: bool,
+    /// Depth of the input signal, 8 to 24 bits.
+    pub lsb_depth: u8,
+    pub frame_duration: Option<FrameDuration>,
+    pub prediction_disabled: bool,
+    pub force_channels: Option<Channels>,
+    pub phase_inversion_disabled: bool,
+}
+
+impl Default for EncoderConfig {
+    fn default() -> Self {
+        EncoderConfig {
+            bitrate: Bitrate::Auto,
+            vbr: true,
+            vbr_constraint: true,
+            complexity: 9,
+            max_bandwidth: Bandwidth::Fullband,
+            signal: None,
+            inband_fec: false,
+            packet_loss_perc: 0,
+            dtx: false,
+            lsb_depth: 24,
+            frame_duration: None,
+            prediction_disabled: false,
+            force_channels: None,
+            phase_inversion_disabled: false,
+        }
+    }
+}
+
+impl EncoderConfig {
+    /// Checks every setting is in the range libopus accepts for a
+    /// single-stream [`Encoder`].
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_up_to(OpusEncoder::MAX_BITRATE)
+    }
+
+    fn validate_up_to(&self, max_bitrate: i32) -> Result<(), Error> {
+        let bitrate_ok = match self.bitrate {
+            Bitrate::BitsPerSecond(bps) => (500..=max_bitrate).contains(&bps),
+            Bitrate::Auto | Bitrate::Max => true,
+        };
+        if bitrate_ok
+            && self.complexity <= 10
+            && self.packet_loss_perc <= 100
+            && (8..=24).contains(&self.lsb_depth)
+        {
+            Ok(())
+        } else {
+            Err(Error::BadArg)
+        }
+    }
+
+    /// Validates and applies every setting to `st`.
+    ///
+    /// # Safety
+    ///
+    /// `st` must point to a valid, initialized encoder.
+    pub(crate) unsafe fn apply<S: ConfigCtls>(&self, st: *mut S) -> Result<(), Error> {
+        self.validate_up_to(S::MAX_BITRATE)?;
+        S::apply(self, st)
+    }
+
+    /// Reads the effective settings back from `st`. The bitrate is the one
+    /// libopus actually targets, never `Auto` or `Max`.
+    ///
+    /// # Safety
+    ///
+    /// `st` must point to a valid, initialized encoder.
+    pub(crate) unsafe fn read<S: ConfigCtls>(st: *mut S) -> Result<Self, Error> {
+        S::read(st)
+    }
+}
+
+/// Encoder state types an [`EncoderConfig`] can be applied to.
+pub(crate) trait ConfigCtls: Sized {
+    /// Highest bitrate accepted, in bits per second.
+    const MAX_BITRATE: i32;
+
+    unsafe fn apply(config: &EncoderConfig, st: *mut Self) -> Result<(), Error>;
+    unsafe fn read(st: *mut Self) -> Result<EncoderConfig, Error>;
+}
+
+/// Implements [`ConfigCtls`] with the functions of a [`ctl`](crate::ctl) module.
+macro_rules! config_ctls {
+    ($state:ty, $ctl:ident, $max_bitrate:expr, $get_max_bandwidth:expr) => {
+        impl ConfigCtls for $state {
+            const MAX_BITRATE: i32 = $max_bitrate;
+
+            unsafe fn apply(config: &EncoderConfig, st: *mut Self) -> Result<(), Error> {
+                use crate::ctl::$ctl::*;
+
+                check(set_bitrate(st, config.bitrate.raw()))?;
+                check(set_vbr(st, config.vbr as opus_int32))?;
+                check(set_vbr_constraint(st, config.vbr_constraint as opus_int32))?;
+                check(set_complexity(st, config.complexity.into()))?;
+                check(set_max_bandwidth(st, config.max_bandwidth.raw()))?;
+                check(set_signal(st, config.signal.map_or(OPUS_AUTO, Signal::raw)))?;
+                check(set_inband_fec(st, config.inband_fec as opus_int32))?;
+                check(set_packet_loss_perc(st, config.packet_loss_perc.into()))?;
+                check(set_dtx(st, config.dtx as opus_int32))?;
+                check(set_lsb_depth(st, config.lsb_depth.into()))?;
+                let frame_duration = config
+                    .frame_duration
+                    .map_or(OPUS_FRAMESIZE_ARG as c_int, FrameDuration::raw);
+                check(set_expert_frame_duration(st, frame_duration))?;
+                check(set_prediction_disabled(
+                    st,
+                    config.prediction_disabled as opus_int32,
+                ))?;
+                let force_channels = config.force_channels.map_or(OPUS_AUTO, Channels::raw);
+                check(set_force_channels(st, force_channels))?;
+                check(set_phase_inversion_disabled(
+                    st,
+                    config.phase_inversion_disabled as opus_int32,
+                ))?;
+                Ok(())
+            }
+
+            unsafe fn read(st: *mut Self) -> Result<EncoderConfig, Error> {
+                use crate::ctl::$ctl::*;
+
+                let signal = get(st, get_signal)?;
+                let frame_duration = get(st, get_expert_frame_duration)?;
+                let force_channels = get(st, get_force_channels)?;
+                Ok(EncoderConfig {
+                    bitrate: Bitrate::try_from(get(st, get_bitrate)?)?,
+                    vbr: get(st, get_vbr)? != 0,
+                    vbr_constraint: get(st, get_vbr_constraint)? != 0,
+                    complexity: get(st, get_complexity)? as u8,
+                    max_bandwidth: Bandwidth::try_from($get_max_bandwidth(st)?)?,
+                    signal: match signal {
+                        OPUS_AUTO => None,
+                        raw => Some(Signal::try_from(raw)?),
+                    },
+                    inband_fec: get(st, get_inband_fec)? != 0,
+                    packet_loss_perc: get(st, get_packet_loss_perc)? as u8,
+                    dtx: get(st, get_dtx)? != 0,
+                    lsb_depth: get(st, get_lsb_depth)? as u8,
+                    frame_duration: if frame_duration == OPUS_FRAMESIZE_ARG as c_int {
+                        None
+                    } else {
+                        Some(FrameDuration::try_from(frame_duration)?)
+                    },
+                    prediction_disabled: get(st, get_prediction_disabled)? != 0,
+                    force_channels: match force_channels {
+                        OPUS_AUTO => None,
+                        raw => Some(Channels::try_from(raw)?),
+                    },
+                    phase_inversion_disabled: get(st, get_phase_inversion_disabled)? != 0,
+                })
+            }
+        }
+    };
+}
+
+config_ctls!(OpusEncoder, encoder, 512_000, |st| get(
+    st,
+    get_max_bandwidth
+));
+// The total of multistream encoders grows with the channels, libopus clamps
+// it to 300 kb/s per channel.
+config_ctls!(OpusMSEncoder, multistream_encoder, i32::MAX, |st| {
+    first_stream_max_bandwidth(st, get_encoder_state)
+});
+config_ctls!(OpusProjectionEncoder, projection_encoder, i32::MAX, |st| {
+    first_stream_max_bandwidth(st, get_encoder_state)
+});
+
+/// libopus does not implement `OPUS_GET_MAX_BANDWIDTH` for multistream
+/// encoders, the setter applies it to every stream so the first one has it.
+unsafe fn first_stream_max_bandwidth<S>(
+    st: *mut S,
+    get_encoder_state: unsafe fn(*mut S, opus_int32, &mut *mut OpusEncoder) -> c_int,
+) -> Result<opus_int32, Error> {
+    let mut first = ptr::null_mut();
+    check(get_encoder_state(st, 0, &mut first))?;
+    get(first, ctl::encoder::get_max_bandwidth)
+}
+
+unsafe fn get<S>(
+    st: *mut S,
+    ctl: unsafe fn(*mut S, &mut opus_int32) -> c_int,
+) -> Result<opus_int32, Error> {
+    let mut value = 0;
+    check(ctl(st, &mut value))?;
+    Ok(value)
+}
+
+/// Creates an [`Encoder`] and applies an [`EncoderConfig`] to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderBuilder {
+    sample_rate: SampleRate,
+    channels: Channels,
+    application: Application,
+    config: EncoderConfig,
+}
+
+impl EncoderBuilder {
+    pub fn new(sample_rate: SampleRate, channels: Channels, application: Application) -> Self {
+        EncoderBuilder {
+            sample_rate,
+            channels,
+            application,
+            config: EncoderConfig::default(),
+        }
+    }
+
+    /// Replaces every setting at once.
+    pub fn config(mut self, config: EncoderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn bitrate(mut self, bitrate: Bitrate) -> Self {
+        self.config.bitrate = bitrate;
+        self
+    }
+
+    pub fn vbr(mut self, vbr: bool) -> Self {
+        self.config.vbr = vbr;
+        self
+    }
+
+    pub fn vbr_constraint(mut self, vbr_constraint: bool) -> Self {
+        self.config.vbr_constraint = vbr_constraint;
+        self
+    }
+
+    pub fn complexity(mut self, complexity: u8) -> Self {
+        self.config.complexity = complexity;
+        self
+    }
+
+    pub fn max_bandwidth(mut self, max_bandwidth: Bandwidth) -> Self {
+        self.config.max_bandwidth = max_bandwidth;
+        self
+    }
+
+    pub fn signal(mut self, signal: Option<Signal>) -> Self {
+        self.config.signal = signal;
+        self
+    }
+
+    pub fn inband_fec(mut self, inband_fec: bool) -> Self {
+        self.config.inband_fec = inband_fec;
+        self
+    }
+
+    pub fn packet_loss_perc(mut self, packet_loss_perc: u8) -> Self {
+        self.config.packet_loss_perc = packet_loss_perc;
+        self
+    }
+
+    pub fn dtx(mut self, dtx: bool) -> Self {
+        self.config.dtx = dtx;
+        self
+    }
+
+    pub fn lsb_depth(mut self, lsb_depth: u8) -> Self {
+        self.config.lsb_depth = lsb_depth;
+        self
+    }
+
+    pub fn frame_duration(mut self, frame_duration: Option<FrameDuration>) -> Self {
+        self.config.frame_duration = frame_duration;
+        self
+    }
+
+    pub fn prediction_disabled(mut self, prediction_disabled: bool) -> Self {
+        self.config.prediction_disabled = prediction_disabled;
+        self
+    }
+
+    pub fn force_channels(mut self, force_channels: Option<Channels>) -> Self {
+        self.config.force_channels = force_channels;
+        self
+    }
+
+    pub fn phase_inversion_disabled(mut self, phase_inversion_disabled: bool) -> Self {
+        self.config.phase_inversion_disabled = phase_inversion_disabled;
+        self
+    }
+
+    pub fn build(&self) -> Result<Encoder, Error> {
+        self.config.validate()?;
+        let mut encoder = Encoder::new(self.sample_rate, self.channels, self.application)?;
+        encoder.apply_config(&self.config)?;
+        Ok(encoder)
+    }
+}
+
+impl Encoder {
+    pub fn builder(
+        sample_rate: SampleRate,
+        channels: Channels,
+        application: Application,
+    ) -> EncoderBuilder {
+        EncoderBuilder::new(sample_rate, channels, application)
+    }
+}
+
+impl<M> Encoder<M> {
+    /// Applies every setting of `config`, e.g. after changing some of them on a
+    /// running stream. Settings after the first one libopus rejects are not applied.
+    pub fn apply_config(&mut self, config: &EncoderConfig) -> Result<(), Error> {
+        unsafe { config.apply(self.as_mut_ptr()) }
+    }
+
+    /// Effective settings, read back from libopus.
+    pub fn config(&mut self) -> Result<EncoderConfig, Error> {
+        unsafe { EncoderConfig::read(self.as_mut_ptr()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every setting changed from its default.
+    fn changed() -> EncoderConfig {
+        EncoderConfig {
+            bitrate: Bitrate::BitsPerSecond(24000),
+            vbr: false,
+            vbr_constraint: false,
+            complexity: 3,
+            max_bandwidth: Bandwidth::Wideband,
+            signal: Some(Signal::Voice),
+            inband_fec: true,
+            packet_loss_perc: 15,
+            dtx: true,
+            lsb_depth: 16,
+            frame_duration: Some(FrameDuration::Ms10),
+            prediction_disabled: true,
+            force_channels: Some(Channels::Mono),
+            phase_inversion_disabled: true,
+        }
+    }
+
+    #[test]
+    fn apply_reads_back_every_field() {
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        let config = changed();
+        encoder.apply_config(&config).unwrap();
+        assert_eq!(encoder.config().unwrap(), config);
+
+        let built = Encoder::builder(SampleRate::Hz48000, Channels::Stereo, Application::Audio)
+            .config(config)
+            .build()
+            .unwrap()
+            .config()
+            .unwrap();
+        assert_eq!(built, config);
+
+        let bitrate = EncoderConfig {
+            bitrate: Bitrate::BitsPerSecond(64000),
+            ..EncoderConfig::default()
+        };
+        encoder.apply_config(&bitrate).unwrap();
+        assert_eq!(encoder.config().unwrap(), bitrate);
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_values() {
+        assert_eq!(EncoderConfig::default().validate(), Ok(()));
+        assert_eq!(changed().validate(), Ok(()));
+
+        let invalid = [
+            EncoderConfig {
+                bitrate: Bitrate::BitsPerSecond(499),
+                ..EncoderConfig::default()
+            },
+            EncoderConfig {
+                bitrate: Bitrate::BitsPerSecond(512_001),
+                ..EncoderConfig::default()
+            },
+            EncoderConfig {
+                complexity: 11,
+                ..EncoderConfig::default()
+            },
+            EncoderConfig {
+                packet_loss_perc: 101,
+                ..EncoderConfig::default()
+            },
+            EncoderConfig {
+                lsb_depth: 7,
+                ..EncoderConfig::default()
+            },
+            EncoderConfig {
+                lsb_depth: 25,
+                ..EncoderConfig::default()
+            },
+        ];
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Audio).unwrap();
+        let before = encoder.config().unwrap();
+        for config in &invalid {
+            assert_eq!(config.validate(), Err(Error::BadArg), "{:?}", config);
+            assert_eq!(encoder.apply_config(config), Err(Error::BadArg));
+            let builder = Encoder::builder(SampleRate::Hz48000, Channels::Mono, Application::Audio);
+            assert_eq!(builder.config(*config).build().err(), Some(Error::BadArg));
+        }
+        assert_eq!(encoder.config().unwrap(), before);
+    }
+}
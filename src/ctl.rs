@@ -0,0 +1,230 @@
+//! Typed CTLs.
+//!
+//! `opus_defines.h` builds CTL calls with function-like macros (`OPUS_SET_BITRATE(x)`),
+//! which bindgen can't translate, leaving the variadic `opus_*_ctl` functions
+//! and bare request numbers. Here every CTL of the 1.3.1 headers is a function
+//! with the argument type the request expects, one module per state type.
+//!
+//! The functions return the raw status, `OPUS_OK` or a negative error code.
+//! `st` must point to a valid, initialized state of the module's type.
+#![allow(clippy::missing_safety_doc)]
+
+use std::os::raw::c_int;
+
+use crate::sys::*;
+
+macro_rules! ctl_fns {
+    ($state:ty, $ctl:ident; $($(#[$meta:meta])* $kind:ident $name:ident($request:ident $(, $ty:ty)?);)*) => {
+        $(ctl_fns!(@fn $state, $ctl, $(#[$meta])* $kind $name($request $(, $ty)?));)*
+    };
+    (@fn $state:ty, $ctl:ident, $(#[$meta:meta])* set $name:ident($request:ident, $ty:ty)) => {
+        $(#[$meta])*
+        pub unsafe fn $name(st: *mut $state, value: $ty) -> c_int {
+            $ctl(st, $request as c_int, value)
+        }
+    };
+    (@fn $state:ty, $ctl:ident, $(#[$meta:meta])* get $name:ident($request:ident, $ty:ty)) => {
+        $(#[$meta])*
+        pub unsafe fn $name(st: *mut $state, value: &mut $ty) -> c_int {
+            $ctl(st, $request as c_int, value as *mut $ty)
+        }
+    };
+    (@fn $state:ty, $ctl:ident, $(#[$meta:meta])* call $name:ident($request:ident)) => {
+        $(#[$meta])*
+        pub unsafe fn $name(st: *mut $state) -> c_int {
+            $ctl(st, $request as c_int)
+        }
+    };
+}
+
+/// CTLs accepted by every encoder and decoder.
+macro_rules! generic_ctls {
+    ($state:ty, $ctl:ident) => {
+        ctl_fns! { $state, $ctl;
+            /// Resets the codec state to be equivalent to a freshly initialized state.
+            call reset_state(OPUS_RESET_STATE);
+            /// Final state of the codec's entropy coder, for testing.
+            get get_final_range(OPUS_GET_FINAL_RANGE_REQUEST, u32);
+            /// Bandpass of the most recently processed packet, `OPUS_BANDWIDTH_*`.
+            get get_bandwidth(OPUS_GET_BANDWIDTH_REQUEST, opus_int32);
+            /// Sampling rate the state was initialized with.
+            get get_sample_rate(OPUS_GET_SAMPLE_RATE_REQUEST, opus_int32);
+            /// Disables (1) or enables (0, default) the use of phase inversion for intensity stereo.
+            set set_phase_inversion_disabled(OPUS_SET_PHASE_INVERSION_DISABLED_REQUEST, opus_int32);
+            get get_phase_inversion_disabled(OPUS_GET_PHASE_INVERSION_DISABLED_REQUEST, opus_int32);
+        }
+    };
+}
+
+macro_rules! encoder_ctls {
+    ($state:ty, $ctl:ident) => {
+        ctl_fns! { $state, $ctl;
+            /// Encoder computational complexity, 0-10.
+            set set_complexity(OPUS_SET_COMPLEXITY_REQUEST, opus_int32);
+            get get_complexity(OPUS_GET_COMPLEXITY_REQUEST, opus_int32);
+            /// Bitrate in bits per second, or `OPUS_AUTO`/`OPUS_BITRATE_MAX`.
+            set set_bitrate(OPUS_SET_BITRATE_REQUEST, opus_int32);
+            get get_bitrate(OPUS_GET_BITRATE_REQUEST, opus_int32);
+            /// Variable (1, default) or constant (0) bitrate.
+            set set_vbr(OPUS_SET_VBR_REQUEST, opus_int32);
+            get get_vbr(OPUS_GET_VBR_REQUEST, opus_int32);
+            /// Constrained (1, default) or unconstrained (0) VBR.
+            set set_vbr_constraint(OPUS_SET_VBR_CONSTRAINT_REQUEST, opus_int32);
+            get get_vbr_constraint(OPUS_GET_VBR_CONSTRAINT_REQUEST, opus_int32);
+            /// Forced mono (1) or stereo (2) coding, or `OPUS_AUTO`.
+            set set_force_channels(OPUS_SET_FORCE_CHANNELS_REQUEST, opus_int32);
+            get get_force_channels(OPUS_GET_FORCE_CHANNELS_REQUEST, opus_int32);
+            /// Maximum bandpass the encoder will select, `OPUS_BANDWIDTH_*`.
+            set set_max_bandwidth(OPUS_SET_MAX_BANDWIDTH_REQUEST, opus_int32);
+            get get_max_bandwidth(OPUS_GET_MAX_BANDWIDTH_REQUEST, opus_int32);
+            /// Forces the bandpass, `OPUS_BANDWIDTH_*` or `OPUS_AUTO`.
+            set set_bandwidth(OPUS_SET_BANDWIDTH_REQUEST, opus_int32);
+            /// Type of signal being encoded, `OPUS_SIGNAL_*` or `OPUS_AUTO`.
+            set set_signal(OPUS_SET_SIGNAL_REQUEST, opus_int32);
+            get get_signal(OPUS_GET_SIGNAL_REQUEST, opus_int32);
+            /// Intended application, `OPUS_APPLICATION_*`.
+            set set_application(OPUS_SET_APPLICATION_REQUEST, opus_int32);
+            get get_application(OPUS_GET_APPLICATION_REQUEST, opus_int32);
+            /// Total samples of delay added by the encoder.
+            get get_lookahead(OPUS_GET_LOOKAHEAD_REQUEST, opus_int32);
+            /// In-band forward error correction, enabled (1) or disabled (0, default).
+            set set_inband_fec(OPUS_SET_INBAND_FEC_REQUEST, opus_int32);
+            get get_inband_fec(OPUS_GET_INBAND_FEC_REQUEST, opus_int32);
+            /// Expected packet loss percentage, 0-100.
+            set set_packet_loss_perc(OPUS_SET_PACKET_LOSS_PERC_REQUEST, opus_int32);
+            get get_packet_loss_perc(OPUS_GET_PACKET_LOSS_PERC_REQUEST, opus_int32);
+            /// Discontinuous transmission, enabled (1) or disabled (0, default).
+            set set_dtx(OPUS_SET_DTX_REQUEST, opus_int32);
+            get get_dtx(OPUS_GET_DTX_REQUEST, opus_int32);
+            /// Depth of the signal being encoded, 8-24 bits.
+            set set_lsb_depth(OPUS_SET_LSB_DEPTH_REQUEST, opus_int32);
+            get get_lsb_depth(OPUS_GET_LSB_DEPTH_REQUEST, opus_int32);
+            /// Frame duration, `OPUS_FRAMESIZE_*`.
+            set set_expert_frame_duration(OPUS_SET_EXPERT_FRAME_DURATION_REQUEST, opus_int32);
+            get get_expert_frame_duration(OPUS_GET_EXPERT_FRAME_DURATION_REQUEST, opus_int32);
+            /// Disables (1) or enables (0, default) inter-frame prediction.
+            set set_prediction_disabled(OPUS_SET_PREDICTION_DISABLED_REQUEST, opus_int32);
+            get get_prediction_disabled(OPUS_GET_PREDICTION_DISABLED_REQUEST, opus_int32);
+        }
+    };
+}
+
+macro_rules! decoder_ctls {
+    ($state:ty, $ctl:ident) => {
+        ctl_fns! { $state, $ctl;
+            /// Output gain in Q8 dB units, -32768 to 32767.
+            set set_gain(OPUS_SET_GAIN_REQUEST, opus_int32);
+            get get_gain(OPUS_GET_GAIN_REQUEST, opus_int32);
+            /// Duration in samples of the last packet successfully decoded or concealed.
+            get get_last_packet_duration(OPUS_GET_LAST_PACKET_DURATION_REQUEST, opus_int32);
+            /// Pitch of the last decoded frame, 0 if not available.
+            get get_pitch(OPUS_GET_PITCH_REQUEST, opus_int32);
+        }
+    };
+}
+
+macro_rules! multistream_encoder_ctls {
+    ($state:ty, $ctl:ident) => {
+        /// Encoder state of stream `stream_id`, owned by `st`.
+        pub unsafe fn get_encoder_state(
+            st: *mut $state,
+            stream_id: opus_int32,
+            value: &mut *mut OpusEncoder,
+        ) -> c_int {
+            $ctl(
+                st,
+                OPUS_MULTISTREAM_GET_ENCODER_STATE_REQUEST as c_int,
+                stream_id,
+                value as *mut *mut OpusEncoder,
+            )
+        }
+    };
+}
+
+macro_rules! multistream_decoder_ctls {
+    ($state:ty, $ctl:ident) => {
+        /// Decoder state of stream `stream_id`, owned by `st`.
+        pub unsafe fn get_decoder_state(
+            st: *mut $state,
+            stream_id: opus_int32,
+            value: &mut *mut OpusDecoder,
+        ) -> c_int {
+            $ctl(
+                st,
+                OPUS_MULTISTREAM_GET_DECODER_STATE_REQUEST as c_int,
+                stream_id,
+                value as *mut *mut OpusDecoder,
+            )
+        }
+    };
+}
+
+/// `OpusEncoder`, [`crate::sys::opus_encoder_ctl`].
+pub mod encoder {
+    use super::*;
+
+    generic_ctls!(OpusEncoder, opus_encoder_ctl);
+    encoder_ctls!(OpusEncoder, opus_encoder_ctl);
+}
+
+/// `OpusDecoder`, [`crate::sys::opus_decoder_ctl`].
+pub mod decoder {
+    use super::*;
+
+    generic_ctls!(OpusDecoder, opus_decoder_ctl);
+    decoder_ctls!(OpusDecoder, opus_decoder_ctl);
+}
+
+/// `OpusMSEncoder`, [`crate::sys::opus_multistream_encoder_ctl`].
+pub mod multistream_encoder {
+    use super::*;
+
+    generic_ctls!(OpusMSEncoder, opus_multistream_encoder_ctl);
+    encoder_ctls!(OpusMSEncoder, opus_multistream_encoder_ctl);
+    multistream_encoder_ctls!(OpusMSEncoder, opus_multistream_encoder_ctl);
+}
+
+/// `OpusMSDecoder`, [`crate::sys::opus_multistream_decoder_ctl`].
+pub mod multistream_decoder {
+    use super::*;
+
+    generic_ctls!(OpusMSDecoder, opus_multistream_decoder_ctl);
+    decoder_ctls!(OpusMSDecoder, opus_multistream_decoder_ctl);
+    multistream_decoder_ctls!(OpusMSDecoder, opus_multistream_decoder_ctl);
+}
+
+/// `OpusProjectionEncoder`, [`crate::sys::opus_projection_encoder_ctl`].
+pub mod projection_encoder {
+    use super::*;
+
+    generic_ctls!(OpusProjectionEncoder, opus_projection_encoder_ctl);
+    encoder_ctls!(OpusProjectionEncoder, opus_projection_encoder_ctl);
+    multistream_encoder_ctls!(OpusProjectionEncoder, opus_projection_encoder_ctl);
+
+    ctl_fns! { OpusProjectionEncoder, opus_projection_encoder_ctl;
+        /// Gain of the demixing matrix, in dB S7.8 format.
+        get get_demixing_matrix_gain(OPUS_PROJECTION_GET_DEMIXING_MATRIX_GAIN_REQUEST, opus_int32);
+        /// Size of the demixing matrix in bytes.
+        get get_demixing_matrix_size(OPUS_PROJECTION_GET_DEMIXING_MATRIX_SIZE_REQUEST, opus_int32);
+    }
+
+    /// Copies the demixing matrix into `matrix`, which must be exactly
+    /// [`get_demixing_matrix_size`] bytes long.
+    pub unsafe fn get_demixing_matrix(st: *mut OpusProjectionEncoder, matrix: &mut [u8]) -> c_int {
+        opus_projection_encoder_ctl(
+            st,
+            OPUS_PROJECTION_GET_DEMIXING_MATRIX_REQUEST as c_int,
+            matrix.as_mut_ptr(),
+            matrix.len() as opus_int32,
+        )
+    }
+}
+
+/// `OpusProjectionDecoder`, [`crate::sys::opus_projection_decoder_ctl`].
+pub mod projection_decoder {
+    use super::*;
+
+    generic_ctls!(OpusProjectionDecoder, opus_projection_decoder_ctl);
+    decoder_ctls!(OpusProjectionDecoder, opus_projection_decoder_ctl);
+    multistream_decoder_ctls!(OpusProjectionDecoder, opus_projection_decoder_ctl);
+}
@@ -0,0 +1,368 @@
+use std::ptr::{self, NonNull};
+
+use crate::codec::{Call, DecoderCore};
+use crate::error::{check, Error};
+use crate::params;
+use crate::sample::Sample;
+use crate::state::{self, BorrowedState, OwnedState};
+use crate::sys::*;
+use crate::types::{Channels, SampleRate};
+
+/// Single-stream decoder.
+///
+/// Output is interleaved PCM in any [`Sample`] format. Every call returns
+/// the number of samples per channel written to the start of `pcm`.
+///
+/// The state lives in memory allocated by Rust ([`OwnedState`]), or in a
+/// caller buffer ([`BorrowedState`], see [`new_in`](Decoder::new_in)).
+#[derive(Debug)]
+pub struct Decoder<M = OwnedState> {
+    core: DecoderCore<OpusDecoder>,
+    _memory: M,
+    channels: Channels,
+}
+
+impl Decoder {
+    pub fn new(sample_rate: SampleRate, channels: Channels) -> Result<Self, Error> {
+        let memory = OwnedState::new(Self::state_size(channels));
+        let state = memory.as_ptr();
+        unsafe { Self::init(state, memory, sample_rate, channels) }
+    }
+
+    /// Bytes of state of a decoder with `channels`, rounded up to
+    /// [`STATE_ALIGN`](crate::STATE_ALIGN).
+    pub fn state_size(channels: Channels) -> usize {
+        state::padded(unsafe { opus_decoder_get_size(channels.raw()) } as usize)
+    }
+}
+
+impl<'a> Decoder<BorrowedState<'a>> {
+    /// Initializes a decoder inside `buf`, starting at its first
+    /// [`STATE_ALIGN`](crate::STATE_ALIGN)-aligned byte. `buf` must hold
+    /// [`state_size`](Decoder::state_size) bytes from there.
+    pub fn new_in(
+        buf: &'a mut [u8],
+        sample_rate: SampleRate,
+        channels: Channels,
+    ) -> Result<Self, Error> {
+        let (memory, state) = BorrowedState::new(buf, Decoder::state_size(channels))?;
+        unsafe { Self::init(state, memory, sample_rate, channels) }
+    }
+}
+
+impl<M> Decoder<M> {
+    /// Initializes the state at `state`, owned or borrowed by `memory`.
+    unsafe fn init(
+        state: *mut u8,
+        memory: M,
+        sample_rate: SampleRate,
+        channels: Channels,
+    ) -> Result<Self, Error> {
+        let state = NonNull::new(state as *mut OpusDecoder).ok_or(Error::AllocFail)?;
+        check(opus_decoder_init(
+            state.as_ptr(),
+            sample_rate.raw(),
+            channels.raw(),
+        ))?;
+
+        Ok(Decoder {
+            core: DecoderCore::new(state, sample_rate, channels.count()),
+            _memory: memory,
+            channels,
+        })
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.core.sample_rate()
+    }
+
+    pub fn channels(&self) -> Channels {
+        self.channels
+    }
+
+    /// Adds TPDF dither when decoding to integer formats. `i16` output is then
+    /// decoded through float too. Off by default.
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.core.set_dither(enabled);
+    }
+
+    /// Samples per channel `packet` decodes to.
+    pub fn nb_samples(&self, packet: &[u8]) -> Result<usize, Error> {
+        let len = params::packet_len(packet)?;
+        let samples =
+            unsafe { opus_decoder_get_nb_samples(self.core.as_ptr(), packet.as_ptr(), len) };
+        Ok(check(samples)? as usize)
+    }
+
+    /// Decodes `packet`, `pcm` must hold at least [`nb_samples`](Self::nb_samples)
+    /// per channel or [`Error::BufferTooSmall`] is returned.
+    pub fn decode<T: Sample>(&mut self, packet: &[u8], pcm: &mut [T]) -> Result<usize, Error> {
+        self.core.call(packet, pcm, Call::Decode)
+    }
+
+    /// Float version of [`decode`](Self::decode).
+    pub fn decode_float(&mut self, packet: &[u8], pcm: &mut [f32]) -> Result<usize, Error> {
+        self.decode(packet, pcm)
+    }
+
+    /// Decodes `packet` into planar PCM, one slice per channel. Each must hold
+    /// at least [`nb_samples`](Self::nb_samples). Deinterleaving goes through a
+    /// buffer kept by the decoder.
+    pub fn decode_planar<T: Sample>(
+        &mut self,
+        packet: &[u8],
+        pcm: &mut [&mut [T]],
+    ) -> Result<usize, Error> {
+        self.core.planar(packet, pcm, Call::Decode)
+    }
+
+    /// Packet loss concealment: synthesizes a lost frame filling all of `pcm`.
+    /// Its duration must be a multiple of 2.5 ms.
+    pub fn conceal<T: Sample>(&mut self, pcm: &mut [T]) -> Result<usize, Error> {
+        self.core.call(&[], pcm, Call::Lost { fec: false })
+    }
+
+    /// Float version of [`conceal`](Self::conceal).
+    pub fn conceal_float(&mut self, pcm: &mut [f32]) -> Result<usize, Error> {
+        self.conceal(pcm)
+    }
+
+    /// Planar version of [`conceal`](Self::conceal), filling all of every
+    /// slice.
+    pub fn conceal_planar<T: Sample>(&mut self, pcm: &mut [&mut [T]]) -> Result<usize, Error> {
+        self.core.planar(&[], pcm, Call::Lost { fec: false })
+    }
+
+    /// Recovers the frame lost right before `next_packet` from its in-band FEC
+    /// data, filling all of `pcm`. Its duration must be a multiple of 2.5 ms
+    /// and should match the lost audio. Without FEC data in `next_packet`
+    /// this falls back to concealment. `next_packet` still has to be passed
+    /// to [`decode`](Self::decode) afterwards.
+    pub fn decode_fec<T: Sample>(
+        &mut self,
+        next_packet: &[u8],
+        pcm: &mut [T],
+    ) -> Result<usize, Error> {
+        self.core.call(next_packet, pcm, Call::Lost { fec: true })
+    }
+
+    /// Float version of [`decode_fec`](Self::decode_fec).
+    pub fn decode_fec_float(
+        &mut self,
+        next_packet: &[u8],
+        pcm: &mut [f32],
+    ) -> Result<usize, Error> {
+        self.decode_fec(next_packet, pcm)
+    }
+
+    /// Planar version of [`decode_fec`](Self::decode_fec), filling all of
+    /// every slice.
+    pub fn decode_fec_planar<T: Sample>(
+        &mut self,
+        next_packet: &[u8],
+        pcm: &mut [&mut [T]],
+    ) -> Result<usize, Error> {
+        self.core.planar(next_packet, pcm, Call::Lost { fec: true })
+    }
+
+    /// Owned copy of the decoder, state included. Either one continues from
+    /// the same point, and the copy can be moved to another thread.
+    pub fn snapshot(&self) -> Decoder {
+        let size = Decoder::state_size(self.channels);
+        let memory = unsafe { OwnedState::copy_of(self.core.as_ptr() as *const u8, size) };
+        Decoder {
+            core: self.core.with_state(memory.as_state()),
+            _memory: memory,
+            channels: self.channels,
+        }
+    }
+
+    /// Puts the decoder back in the state of `snapshot`, which must have the
+    /// same sample rate and channel count or [`Error::BadArg`] is returned.
+    pub fn restore<N>(&mut self, snapshot: &Decoder<N>) -> Result<(), Error> {
+        if snapshot.sample_rate() != self.sample_rate() || snapshot.channels != self.channels {
+            return Err(Error::BadArg);
+        }
+        let size = Decoder::state_size(self.channels);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                snapshot.core.as_ptr() as *const u8,
+                self.core.as_ptr() as *mut u8,
+                size,
+            )
+        };
+        Ok(())
+    }
+
+    /// Raw state for [`ctl::decoder`](crate::ctl::decoder) and other raw calls.
+    /// It stays owned by the decoder.
+    pub fn as_mut_ptr(&mut self) -> *mut OpusDecoder {
+        self.core.as_ptr()
+    }
+}
+
+impl Clone for Decoder {
+    fn clone(&self) -> Self {
+        self.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::sample;
+    use crate::testing::encode_sine;
+    use crate::types::Application;
+
+    /// Stereo 20 ms packets of a sine, at 48 kHz.
+    fn packets(count: usize) -> Vec<Vec<u8>> {
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        encode_sine(&mut encoder, count)
+    }
+
+    fn split<T: Copy + Default>(interleaved: &[T]) -> [Vec<T>; 2] {
+        let mut planes = [
+            vec![T::default(); interleaved.len() / 2],
+            vec![T::default(); interleaved.len() / 2],
+        ];
+        let planes_mut = planes.iter_mut().map(|plane| &mut plane[..]);
+        sample::deinterleave(interleaved, 2, planes_mut, |sample| sample);
+        planes
+    }
+
+    #[test]
+    fn interleave_deinterleave_round_trip() {
+        let left: Vec<i16> = (0..480).collect();
+        let right: Vec<i16> = (0..480).map(|i| -i).collect();
+        let mut interleaved = Vec::new();
+        let planes = [&left[..], &right[..]];
+        sample::interleave(planes.iter().copied(), 2, 480, &mut interleaved, |s| s);
+        assert_eq!(&interleaved[..4], &[0, 0, 1, -1]);
+
+        let [left_out, right_out] = split(&interleaved);
+        assert_eq!(left_out, left);
+        assert_eq!(right_out, right);
+    }
+
+    #[test]
+    fn planar_calls_match_interleaved() {
+        let packets = packets(3);
+        let mut interleaved = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        let mut planar = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        let mut pcm = vec![0i16; 960 * 2];
+        let mut left = vec![0i16; 960];
+        let mut right = vec![0i16; 960];
+
+        assert_eq!(interleaved.decode(&packets[0], &mut pcm).unwrap(), 960);
+        let samples = planar
+            .decode_planar(&packets[0], &mut [&mut left, &mut right])
+            .unwrap();
+        assert_eq!(samples, 960);
+        assert_eq!(split(&pcm), [left.clone(), right.clone()]);
+
+        assert_eq!(interleaved.conceal(&mut pcm).unwrap(), 960);
+        let samples = planar.conceal_planar(&mut [&mut left, &mut right]).unwrap();
+        assert_eq!(samples, 960);
+        assert_eq!(split(&pcm), [left.clone(), right.clone()]);
+
+        let mut pcm = vec![0f32; 960 * 2];
+        let mut left = vec![0f32; 960];
+        let mut right = vec![0f32; 960];
+        assert_eq!(interleaved.decode_fec(&packets[2], &mut pcm).unwrap(), 960);
+        let samples = planar
+            .decode_fec_planar(&packets[2], &mut [&mut left, &mut right])
+            .unwrap();
+        assert_eq!(samples, 960);
+        assert_eq!(split(&pcm), [left, right]);
+    }
+
+    #[test]
+    fn planar_rejects_mismatched_planes() {
+        let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        let mut left = vec![0i16; 960];
+        let mut right = vec![0i16; 480];
+        assert_eq!(
+            decoder.conceal_planar(&mut [&mut left, &mut right]),
+            Err(Error::BadArg)
+        );
+        assert_eq!(decoder.conceal_planar(&mut [&mut left]), Err(Error::BadArg));
+    }
+
+    #[test]
+    fn snapshot_decodes_identical_samples() {
+        let packets = packets(4);
+        let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        let mut pcm = vec![0i16; 960 * 2];
+        for packet in &packets[..3] {
+            decoder.decode(packet, &mut pcm).unwrap();
+        }
+
+        let snapshot = decoder.snapshot();
+        let mut first = vec![0i16; 960 * 2];
+        decoder.decode(&packets[3], &mut first).unwrap();
+        let mut second = vec![0i16; 960 * 2];
+        snapshot.clone().decode(&packets[3], &mut second).unwrap();
+        assert_eq!(first, second);
+
+        decoder.restore(&snapshot).unwrap();
+        decoder.decode(&packets[3], &mut second).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn restore_rejects_other_layout() {
+        let mut mono = Decoder::new(SampleRate::Hz48000, Channels::Mono).unwrap();
+        let stereo = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        assert_eq!(mono.restore(&stereo), Err(Error::BadArg));
+
+        let mut narrowband = Decoder::new(SampleRate::Hz8000, Channels::Mono).unwrap();
+        assert_eq!(narrowband.restore(&mono), Err(Error::BadArg));
+        assert_eq!(narrowband.sample_rate(), SampleRate::Hz8000);
+        assert_eq!(narrowband.conceal(&mut [0i16; 160]), Ok(160));
+    }
+
+    #[test]
+    fn decode_rejects_short_output() {
+        let packets = packets(2);
+        let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        let mut pcm = vec![0i16; 959 * 2];
+        assert_eq!(
+            decoder.decode(&packets[0], &mut pcm),
+            Err(Error::BufferTooSmall)
+        );
+        let mut planes = [vec![0f32; 959], vec![0f32; 959]];
+        let [left, right] = &mut planes;
+        assert_eq!(
+            decoder.decode_planar(&packets[0], &mut [left, right]),
+            Err(Error::BufferTooSmall)
+        );
+        assert_eq!(
+            decoder.decode::<i16>(&packets[0], &mut []),
+            Err(Error::BufferTooSmall)
+        );
+
+        // A longer buffer is fine, only the frame is written.
+        let mut pcm = vec![0i16; 1200 * 2];
+        assert_eq!(decoder.decode(&packets[0], &mut pcm).unwrap(), 960);
+        assert_eq!(
+            decoder
+                .decode_float(&packets[1], &mut vec![0f32; 960 * 2])
+                .unwrap(),
+            960
+        );
+    }
+
+    #[test]
+    fn conceal_rejects_invalid_durations() {
+        let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        for &samples in &[0, 119, 121, 500] {
+            let mut pcm = vec![0i16; samples * 2];
+            assert_eq!(decoder.conceal(&mut pcm), Err(Error::BadArg));
+        }
+        // Not a whole number of stereo samples.
+        assert_eq!(decoder.conceal(&mut [0i16; 241]), Err(Error::BadArg));
+        assert_eq!(decoder.conceal(&mut [0i16; 240]).unwrap(), 120);
+    }
+}
@@ -0,0 +1,273 @@
+use std::ptr::{self, NonNull};
+
+use crate::codec::EncoderCore;
+use crate::error::{check, Error};
+use crate::packet;
+use crate::sample::Sample;
+use crate::state::{self, BorrowedState, OwnedState};
+use crate::sys::*;
+use crate::types::{Application, Channels, SampleRate};
+
+/// Single-stream encoder.
+///
+/// Input is interleaved PCM in any [`Sample`] format, the frame size is taken
+/// from the input length and must be 2.5, 5, 10, 20, 40, 60, 80, 100 or 120 ms.
+///
+/// The state lives in memory allocated by Rust ([`OwnedState`]), or in a
+/// caller buffer ([`BorrowedState`], see [`new_in`](Encoder::new_in)).
+#[derive(Debug)]
+pub struct Encoder<M = OwnedState> {
+    core: EncoderCore<OpusEncoder>,
+    _memory: M,
+    channels: Channels,
+    pad_to: Option<usize>,
+}
+
+impl Encoder {
+    pub fn new(
+        sample_rate: SampleRate,
+        channels: Channels,
+        application: Application,
+    ) -> Result<Self, Error> {
+        let memory = OwnedState::new(Self::state_size(channels));
+        let state = memory.as_ptr();
+        unsafe { Self::init(state, memory, sample_rate, channels, application) }
+    }
+
+    /// Bytes of state of an encoder with `channels`, rounded up to
+    /// [`STATE_ALIGN`](crate::STATE_ALIGN).
+    pub fn state_size(channels: Channels) -> usize {
+        state::padded(unsafe { opus_encoder_get_size(channels.raw()) } as usize)
+    }
+}
+
+impl<'a> Encoder<BorrowedState<'a>> {
+    /// Initializes an encoder inside `buf`, starting at its first
+    /// [`STATE_ALIGN`](crate::STATE_ALIGN)-aligned byte. `buf` must hold
+    /// [`state_size`](Encoder::state_size) bytes from there.
+    pub fn new_in(
+        buf: &'a mut [u8],
+        sample_rate: SampleRate,
+        channels: Channels,
+        application: Application,
+    ) -> Result<Self, Error> {
+        let (memory, state) = BorrowedState::new(buf, Encoder::state_size(channels))?;
+        unsafe { Self::init(state, memory, sample_rate, channels, application) }
+    }
+}
+
+impl<M> Encoder<M> {
+    /// Initializes the state at `state`, owned or borrowed by `memory`.
+    unsafe fn init(
+        state: *mut u8,
+        memory: M,
+        sample_rate: SampleRate,
+        channels: Channels,
+        application: Application,
+    ) -> Result<Self, Error> {
+        let state = NonNull::new(state as *mut OpusEncoder).ok_or(Error::AllocFail)?;
+        check(opus_encoder_init(
+            state.as_ptr(),
+            sample_rate.raw(),
+            channels.raw(),
+            application.raw(),
+        ))?;
+
+        Ok(Encoder {
+            core: EncoderCore::new(state, sample_rate, channels.count()),
+            _memory: memory,
+            channels,
+            pad_to: None,
+        })
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.core.sample_rate()
+    }
+
+    pub fn channels(&self) -> Channels {
+        self.channels
+    }
+
+    /// Pads every packet to exactly `size` bytes, or stops padding with `None`.
+    /// Packets are then encoded in at most `size` bytes, and `output` must
+    /// hold that many.
+    pub fn set_pad_to(&mut self, size: Option<usize>) {
+        self.pad_to = size;
+    }
+
+    pub fn pad_to(&self) -> Option<usize> {
+        self.pad_to
+    }
+
+    /// Encodes one frame of PCM into `output`, returns the packet length.
+    pub fn encode<T: Sample>(&mut self, pcm: &[T], output: &mut [u8]) -> Result<usize, Error> {
+        self.padded(output, |core, output| core.encode(pcm, output))
+    }
+
+    /// Float version of [`encode`](Self::encode), input in the +/-1.0 range.
+    pub fn encode_float(&mut self, pcm: &[f32], output: &mut [u8]) -> Result<usize, Error> {
+        self.encode(pcm, output)
+    }
+
+    /// Encodes one frame of planar PCM, one slice per channel, into `output`.
+    /// Interleaving goes through a buffer kept by the encoder.
+    pub fn encode_planar<T: Sample>(
+        &mut self,
+        pcm: &[&[T]],
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.padded(output, |core, output| core.planar(pcm, output))
+    }
+
+    /// Owned copy of the encoder, state included. Either one continues from
+    /// the same point, and the copy can be moved to another thread.
+    pub fn snapshot(&self) -> Encoder {
+        let size = Encoder::state_size(self.channels);
+        let memory = unsafe { OwnedState::copy_of(self.core.as_ptr() as *const u8, size) };
+        Encoder {
+            core: self.core.with_state(memory.as_state()),
+            _memory: memory,
+            channels: self.channels,
+            pad_to: self.pad_to,
+        }
+    }
+
+    /// Puts the encoder back in the state of `snapshot`, which must have the
+    /// same sample rate and channel count or [`Error::BadArg`] is returned.
+    pub fn restore<N>(&mut self, snapshot: &Encoder<N>) -> Result<(), Error> {
+        if snapshot.sample_rate() != self.sample_rate() || snapshot.channels != self.channels {
+            return Err(Error::BadArg);
+        }
+        let size = Encoder::state_size(self.channels);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                snapshot.core.as_ptr() as *const u8,
+                self.core.as_ptr() as *mut u8,
+                size,
+            )
+        };
+        Ok(())
+    }
+
+    /// Raw state for [`ctl::encoder`](crate::ctl::encoder) and other raw calls.
+    /// It stays owned by the encoder.
+    pub fn as_mut_ptr(&mut self) -> *mut OpusEncoder {
+        self.core.as_ptr()
+    }
+
+    /// Runs `encode` on `output`, cut to [`pad_to`](Self::pad_to) bytes and
+    /// padded to all of them when set.
+    fn padded<F>(&mut self, output: &mut [u8], encode: F) -> Result<usize, Error>
+    where
+        F: FnOnce(&mut EncoderCore<OpusEncoder>, &mut [u8]) -> Result<usize, Error>,
+    {
+        let output = match self.pad_to {
+            Some(size) => output.get_mut(..size).ok_or(Error::BufferTooSmall)?,
+            None => output,
+        };
+        let len = encode(&mut self.core, output)?;
+        if self.pad_to.is_some() {
+            packet::pad(output, len)?;
+            return Ok(output.len());
+        }
+        Ok(len)
+    }
+}
+
+impl Clone for Encoder {
+    fn clone(&self) -> Self {
+        self.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{encode_sine, sine};
+    use crate::types::{Application, FrameDuration};
+
+    #[test]
+    fn snapshot_encodes_identical_packets() {
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Audio).unwrap();
+        encode_sine(&mut encoder, 5);
+
+        let snapshot = encoder.snapshot();
+        let mut first = [0; 1500];
+        let mut second = [0; 1500];
+        let len = encoder.encode(&sine(5, 1), &mut first).unwrap();
+        let mut copy = snapshot.clone();
+        assert_eq!(copy.encode(&sine(5, 1), &mut second).unwrap(), len);
+        assert_eq!(first[..len], second[..len]);
+
+        encoder.restore(&snapshot).unwrap();
+        second = [0; 1500];
+        assert_eq!(encoder.encode(&sine(5, 1), &mut second).unwrap(), len);
+        assert_eq!(first[..len], second[..len]);
+    }
+
+    #[test]
+    fn restore_rejects_other_layout() {
+        let mut mono =
+            Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Audio).unwrap();
+        let stereo =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        assert_eq!(mono.restore(&stereo), Err(Error::BadArg));
+        assert_eq!(mono.channels(), Channels::Mono);
+
+        let mut wideband =
+            Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Audio).unwrap();
+        wideband.set_pad_to(Some(100));
+        assert_eq!(wideband.restore(&mono), Err(Error::BadArg));
+        assert_eq!(wideband.sample_rate(), SampleRate::Hz16000);
+        let mut output = [0; 1500];
+        assert_eq!(wideband.encode(&[0i16; 320], &mut output), Ok(100));
+    }
+
+    #[test]
+    fn encode_rejects_invalid_frame_sizes() {
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        let mut output = [0; 1500];
+        for &samples in &[0, 120 - 1, 480 + 1, 1000, 5760 + 120] {
+            let pcm = vec![0i16; samples * 2];
+            assert_eq!(encoder.encode(&pcm, &mut output), Err(Error::BadArg));
+        }
+        // Not a whole number of stereo samples.
+        assert_eq!(
+            encoder.encode(&[0i16; 1919], &mut output),
+            Err(Error::BadArg)
+        );
+
+        for &duration in FrameDuration::ALL {
+            let pcm = vec![0i16; duration.samples(SampleRate::Hz48000) * 2];
+            assert!(encoder.encode(&pcm, &mut output).is_ok());
+        }
+        assert_eq!(
+            encoder.encode(&sine(0, 2), &mut []),
+            Err(Error::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn pad_to_fixes_packet_length() {
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        encoder.set_pad_to(Some(400));
+        assert_eq!(encoder.pad_to(), Some(400));
+        let mut output = [0; 1500];
+        for index in 0..3 {
+            assert_eq!(encoder.encode(&sine(index, 2), &mut output), Ok(400));
+            let packet = packet::Packet::new(&output[..400]).unwrap();
+            assert_eq!(packet.nb_samples(SampleRate::Hz48000), 960);
+        }
+        assert_eq!(
+            encoder.encode(&sine(3, 2), &mut output[..399]),
+            Err(Error::BufferTooSmall)
+        );
+
+        encoder.set_pad_to(None);
+        assert!(encoder.encode(&sine(4, 2), &mut output[..399]).unwrap() < 399);
+    }
+}
@@ -0,0 +1,129 @@
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_int;
+
+use crate::sys::*;
+
+/// Error from a safe wrapper, one per `OPUS_*` error code in `opus_defines.h`.
+///
+/// Arguments rejected before reaching libopus are reported the way libopus
+/// would report them, mostly as [`Error::BadArg`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Error {
+    /// `OPUS_BAD_ARG`: one or more invalid or out of range arguments.
+    BadArg,
+    /// `OPUS_BUFFER_TOO_SMALL`: not enough bytes allocated in the buffer.
+    BufferTooSmall,
+    /// `OPUS_INTERNAL_ERROR`: an internal error was detected.
+    InternalError,
+    /// `OPUS_INVALID_PACKET`: the compressed data passed is corrupted.
+    InvalidPacket,
+    /// `OPUS_UNIMPLEMENTED`: invalid or unsupported request number.
+    Unimplemented,
+    /// `OPUS_INVALID_STATE`: an encoder or decoder structure is invalid or already freed.
+    InvalidState,
+    /// `OPUS_ALLOC_FAIL`: memory allocation has failed.
+    AllocFail,
+    /// A negative code libopus doesn't define.
+    Unknown(c_int),
+}
+
+impl Error {
+    /// Maps a negative libopus return value to its variant.
+    pub fn from_code(code: c_int) -> Self {
+        match code {
+            OPUS_BAD_ARG => Error::BadArg,
+            OPUS_BUFFER_TOO_SMALL => Error::BufferTooSmall,
+            OPUS_INTERNAL_ERROR => Error::InternalError,
+            OPUS_INVALID_PACKET => Error::InvalidPacket,
+            OPUS_UNIMPLEMENTED => Error::Unimplemented,
+            OPUS_INVALID_STATE => Error::InvalidState,
+            OPUS_ALLOC_FAIL => Error::AllocFail,
+            code => Error::Unknown(code),
+        }
+    }
+
+    /// The raw `OPUS_*` error code.
+    pub fn code(self) -> c_int {
+        match self {
+            Error::BadArg => OPUS_BAD_ARG,
+            Error::BufferTooSmall => OPUS_BUFFER_TOO_SMALL,
+            Error::InternalError => OPUS_INTERNAL_ERROR,
+            Error::InvalidPacket => OPUS_INVALID_PACKET,
+            Error::Unimplemented => OPUS_UNIMPLEMENTED,
+            Error::InvalidState => OPUS_INVALID_STATE,
+            Error::AllocFail => OPUS_ALLOC_FAIL,
+            Error::Unknown(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // opus_strerror returns a static string for any code.
+        let message = unsafe { CStr::from_ptr(opus_strerror(self.code())) };
+        f.write_str(&message.to_string_lossy())
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Maps a libopus return value, negative on failure, to a `Result`.
+pub(crate) fn check(ret: c_int) -> Result<c_int, Error> {
+    if ret < 0 {
+        Err(Error::from_code(ret))
+    } else {
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODES: &[(c_int, Error)] = &[
+        (OPUS_BAD_ARG, Error::BadArg),
+        (OPUS_BUFFER_TOO_SMALL, Error::BufferTooSmall),
+        (OPUS_INTERNAL_ERROR, Error::InternalError),
+        (OPUS_INVALID_PACKET, Error::InvalidPacket),
+        (OPUS_UNIMPLEMENTED, Error::Unimplemented),
+        (OPUS_INVALID_STATE, Error::InvalidState),
+        (OPUS_ALLOC_FAIL, Error::AllocFail),
+    ];
+
+    #[test]
+    fn codes_round_trip() {
+        for &(code, error) in CODES {
+            assert_eq!(Error::from_code(code), error);
+            assert_eq!(error.code(), code);
+            assert_eq!(check(code), Err(error));
+        }
+        assert_eq!(check(0), Ok(0));
+        assert_eq!(check(960), Ok(960));
+    }
+
+    #[test]
+    fn unknown_code_is_kept() {
+        let error = Error::from_code(-42);
+        assert_eq!(error, Error::Unknown(-42));
+        assert_eq!(error.code(), -42);
+        assert_eq!(error.to_string(), "unknown error");
+    }
+
+    #[test]
+    fn display_uses_libopus_messages() {
+        let messages: Vec<String> = CODES.iter().map(|(_, error)| error.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "invalid argument",
+                "buffer too small",
+                "internal error",
+                "corrupted stream",
+                "request not implemented",
+                "invalid state",
+                "memory allocation failed",
+            ]
+        );
+    }
+}
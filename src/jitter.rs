@@ -0,0 +1,535 @@
+use std::collections::BTreeMap;
+
+use crate::decoder::Decoder;
+use crate::error::Error;
+use crate::packet::{Mode, Packet};
+use crate::sample::Sample;
+use crate::state::OwnedState;
+use crate::types::SampleRate;
+
+/// Clock of the timestamps, the RTP clock rate of Opus.
+const CLOCK_RATE: i64 = 48_000;
+
+/// 2.5 ms at [`CLOCK_RATE`], the granularity of Opus durations.
+const MIN_DURATION: i64 = 120;
+
+/// 120 ms at [`CLOCK_RATE`], the longest packet and the most one pull writes.
+const MAX_DURATION: i64 = 5760;
+
+/// Concealment length before any packet has been decoded, 20 ms.
+const DEFAULT_DURATION: i64 = 960;
+
+/// What [`JitterBuffer::pull`] wrote, in samples per channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playout {
+    /// Still filling up to the target delay, nothing was written.
+    Buffering,
+    /// The next packet was decoded.
+    Decoded(usize),
+    /// Lost audio right before the next packet, rebuilt from its in-band
+    /// FEC data. libopus conceals what the FEC data does not cover.
+    Recovered(usize),
+    /// Lost audio, a DTX gap or an underrun, synthesized by PLC.
+    Concealed(usize),
+}
+
+impl Playout {
+    /// Samples per channel written to `pcm`.
+    pub fn samples(self) -> usize {
+        match self {
+            Playout::Buffering => 0,
+            Playout::Decoded(samples)
+            | Playout::Recovered(samples)
+            | Playout::Concealed(samples) => samples,
+        }
+    }
+}
+
+/// Counters kept by a [`JitterBuffer`] since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct JitterStats {
+    /// Packets accepted by [`insert`](JitterBuffer::insert).
+    pub received: u64,
+    /// Packets dropped because the same sequence number was buffered already.
+    pub duplicates: u64,
+    /// Packets dropped because their playout time had passed.
+    pub late: u64,
+    /// Sequence numbers skipped at playout.
+    pub lost: u64,
+    /// Losses rebuilt from FEC data. Losses right after a CELT packet are
+    /// concealed instead, libopus has no FEC to continue from CELT.
+    pub recovered: u64,
+    /// Pulls filled by PLC.
+    pub concealed: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    timestamp: i64,
+    duration: i64,
+    /// Duration of one frame, what its FEC data covers.
+    frame: i64,
+    fec: bool,
+    mode: Mode,
+    data: Vec<u8>,
+}
+
+/// Jitter buffer in front of a [`Decoder`], for packets from RTP or a
+/// similar transport.
+///
+/// Packets are inserted as they arrive, with their 16-bit sequence number
+/// and 32-bit timestamp at 48 kHz, both wrapping around. They are put back
+/// in order, and playout starts once the target delay is buffered. From
+/// then on every [`pull`](Self::pull) writes the next piece of audio:
+/// a decoded packet, or for a missing one the FEC data of the packet after
+/// it if there is one, or else PLC. Gaps in the timestamps without missing
+/// sequence numbers, as left by DTX, are concealed without counting as loss.
+#[derive(Debug)]
+pub struct JitterBuffer<M = OwnedState> {
+    decoder: Decoder<M>,
+    target_delay: i64,
+    fec: bool,
+    packets: BTreeMap<i64, Entry>,
+    started: bool,
+    /// Next sequence number to play.
+    next_sequence: i64,
+    /// Timestamp of the next sample to play.
+    playout: i64,
+    /// Highest sequence number and timestamp seen, the references to unwrap
+    /// new ones around.
+    last_sequence: Option<i64>,
+    last_timestamp: i64,
+    last_duration: i64,
+    /// Mode of the last packet decoded, libopus ignores FEC data after CELT.
+    last_mode: Option<Mode>,
+    stats: JitterStats,
+}
+
+impl<M> JitterBuffer<M> {
+    /// Jitter buffer holding `target_delay_ms` of audio before playout
+    /// starts. FEC recovery is on.
+    pub fn new(decoder: Decoder<M>, target_delay_ms: u32) -> Self {
+        JitterBuffer {
+            decoder,
+            target_delay: i64::from(target_delay_ms) * CLOCK_RATE / 1000,
+            fec: true,
+            packets: BTreeMap::new(),
+            started: false,
+            next_sequence: 0,
+            playout: 0,
+            last_sequence: None,
+            last_timestamp: 0,
+            last_duration: DEFAULT_DURATION,
+            last_mode: None,
+            stats: JitterStats::default(),
+        }
+    }
+
+    pub fn decoder(&self) -> &Decoder<M> {
+        &self.decoder
+    }
+
+    pub fn decoder_mut(&mut self) -> &mut Decoder<M> {
+        &mut self.decoder
+    }
+
+    /// Uses the in-band FEC data of the next packet to rebuild a lost one,
+    /// instead of PLC alone.
+    pub fn set_fec(&mut self, enabled: bool) {
+        self.fec = enabled;
+    }
+
+    pub fn stats(&self) -> JitterStats {
+        self.stats
+    }
+
+    /// Packets waiting for playout.
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Adds a received packet. Duplicates and packets whose playout time has
+    /// passed are dropped and counted in the [`stats`](Self::stats).
+    /// Malformed packets are rejected with [`Error::InvalidPacket`].
+    pub fn insert(&mut self, sequence: u16, timestamp: u32, packet: &[u8]) -> Result<(), Error> {
+        let parsed = Packet::new(packet)?;
+        let duration = parsed.nb_samples(SampleRate::Hz48000) as i64;
+        let frame = parsed.samples_per_frame(SampleRate::Hz48000) as i64;
+        let fec = parsed.has_fec();
+        let mode = parsed.mode();
+
+        let (sequence, timestamp) = match self.last_sequence {
+            Some(last_sequence) => {
+                let sequence =
+                    last_sequence + i64::from(sequence.wrapping_sub(last_sequence as u16) as i16);
+                let timestamp = self.last_timestamp
+                    + i64::from(timestamp.wrapping_sub(self.last_timestamp as u32) as i32);
+                (sequence, timestamp)
+            }
+            None => (i64::from(sequence), i64::from(timestamp)),
+        };
+        if self.started && (sequence < self.next_sequence || timestamp < self.playout) {
+            self.stats.late += 1;
+            return Ok(());
+        }
+        if self.packets.contains_key(&sequence) {
+            self.stats.duplicates += 1;
+            return Ok(());
+        }
+        if self.last_sequence.is_none_or(|last| sequence > last) {
+            self.last_sequence = Some(sequence);
+            self.last_timestamp = timestamp;
+        }
+        self.packets.insert(
+            sequence,
+            Entry {
+                timestamp,
+                duration,
+                frame,
+                fec,
+                mode,
+                data: packet.to_vec(),
+            },
+        );
+        self.stats.received += 1;
+        Ok(())
+    }
+
+    /// Writes the next piece of interleaved audio to `pcm`, which must hold
+    /// 120 ms, the longest packet. Plays a decoded packet, FEC recovery or
+    /// concealment, see [`Playout`].
+    ///
+    /// A packet the decoder fails on is dropped and its error returned, the
+    /// next pull goes on after it. A failed FEC recovery skips the lost frames
+    /// the same way, the packet is decoded by the next pull.
+    pub fn pull<T: Sample>(&mut self, pcm: &mut [T]) -> Result<Playout, Error> {
+        let sample_rate = self.decoder.sample_rate();
+        let channels = self.decoder.channels().count();
+        if pcm.len() < to_rate(MAX_DURATION, sample_rate) * channels {
+            return Err(Error::BufferTooSmall);
+        }
+        if !self.started && !self.start() {
+            return Ok(Playout::Buffering);
+        }
+
+        let first = match self.packets.first_entry() {
+            Some(first) => first,
+            // Underrun, keep the audio going.
+            None => return self.conceal(pcm, self.last_duration),
+        };
+        let sequence = *first.key();
+        let missing = sequence - self.next_sequence;
+        // Whole 2.5 ms up to the next packet.
+        let gap = (first.get().timestamp - self.playout) / MIN_DURATION * MIN_DURATION;
+
+        if gap <= 0 {
+            let entry = first.remove();
+            self.stats.lost += missing as u64;
+            self.next_sequence = sequence + 1;
+            self.playout = self.playout.max(entry.timestamp) + entry.duration;
+            self.last_duration = entry.duration;
+            let len = to_rate(entry.duration, sample_rate) * channels;
+            let samples = self.decoder.decode(&entry.data, &mut pcm[..len])?;
+            self.last_mode = Some(entry.mode);
+            return Ok(Playout::Decoded(samples));
+        }
+
+        // Missing packets may still arrive, so the gap is concealed a packet
+        // at a time. Only the last frame before the next packet is left for
+        // its FEC data, in one call with any remainder.
+        let entry = first.get();
+        let fec = missing > 0
+            && self.fec
+            && entry.fec
+            && gap >= entry.frame
+            && self.last_mode != Some(Mode::Celt);
+        if fec && gap - entry.frame < self.last_duration && gap <= MAX_DURATION {
+            let len = to_rate(gap, sample_rate) * channels;
+            // Past the gap even if recovery fails, the packet is decoded next.
+            self.stats.lost += missing as u64;
+            self.next_sequence = sequence;
+            self.playout += gap;
+            let samples = self.decoder.decode_fec(&entry.data, &mut pcm[..len])?;
+            self.last_mode = Some(entry.mode);
+            self.stats.recovered += 1;
+            return Ok(Playout::Recovered(samples));
+        }
+        let before_fec = if fec { gap - entry.frame } else { gap };
+        self.conceal(pcm, before_fec.min(self.last_duration))
+    }
+
+    /// Starts playout at the first packet if the target delay is buffered.
+    fn start(&mut self) -> bool {
+        let ((&sequence, first), (_, last)) = match (
+            self.packets.first_key_value(),
+            self.packets.last_key_value(),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return false,
+        };
+        if last.timestamp + last.duration - first.timestamp < self.target_delay {
+            return false;
+        }
+        self.playout = first.timestamp;
+        self.next_sequence = sequence;
+        self.started = true;
+        true
+    }
+
+    /// Conceals `duration` at the clock rate.
+    fn conceal<T: Sample>(&mut self, pcm: &mut [T], duration: i64) -> Result<Playout, Error> {
+        let len = to_rate(duration, self.decoder.sample_rate()) * self.decoder.channels().count();
+        let samples = self.decoder.conceal(&mut pcm[..len])?;
+        self.playout += duration;
+        self.stats.concealed += 1;
+        Ok(Playout::Concealed(samples))
+    }
+}
+
+/// `duration` at the clock rate, in samples at `sample_rate`.
+fn to_rate(duration: i64, sample_rate: SampleRate) -> usize {
+    (duration * i64::from(sample_rate.hz()) / CLOCK_RATE) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderBuilder;
+    use crate::testing::encode_sine;
+    use crate::types::{Application, Bandwidth, Bitrate, Channels};
+
+    /// Mono 20 ms packets, SILK with FEC data for the packet before, or CELT.
+    fn encode(count: usize, silk: bool) -> Vec<Vec<u8>> {
+        let application = if silk {
+            Application::Voip
+        } else {
+            Application::RestrictedLowdelay
+        };
+        let mut encoder = EncoderBuilder::new(SampleRate::Hz48000, Channels::Mono, application)
+            .bitrate(Bitrate::BitsPerSecond(20_000))
+            .max_bandwidth(Bandwidth::Wideband)
+            .inband_fec(silk)
+            .packet_loss_perc(if silk { 20 } else { 0 })
+            .build()
+            .unwrap();
+        // Skip the first packets, which have nothing to carry FEC data for.
+        encode_sine(&mut encoder, count + 5).split_off(5)
+    }
+
+    fn buffer(target_delay_ms: u32) -> JitterBuffer {
+        let decoder = Decoder::new(SampleRate::Hz48000, Channels::Mono).unwrap();
+        JitterBuffer::new(decoder, target_delay_ms)
+    }
+
+    /// Pulls until `samples` have been played, returns every playout.
+    fn play(buffer: &mut JitterBuffer, samples: usize) -> Vec<Playout> {
+        let mut pcm = [0i16; 5760];
+        let mut played = 0;
+        let mut playouts = Vec::new();
+        while played < samples {
+            let playout = buffer.pull(&mut pcm).unwrap();
+            assert_ne!(playout, Playout::Buffering);
+            played += playout.samples();
+            playouts.push(playout);
+        }
+        assert_eq!(played, samples);
+        playouts
+    }
+
+    #[test]
+    fn buffers_up_to_target_delay() {
+        let packets = encode(3, true);
+        let mut buffer = buffer(40);
+        let mut pcm = [0i16; 5760];
+        assert_eq!(buffer.pull(&mut pcm), Ok(Playout::Buffering));
+        buffer.insert(0, 0, &packets[0]).unwrap();
+        assert_eq!(buffer.pull(&mut pcm), Ok(Playout::Buffering));
+        buffer.insert(1, 960, &packets[1]).unwrap();
+        assert_eq!(buffer.pull(&mut pcm), Ok(Playout::Decoded(960)));
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn small_output_is_rejected() {
+        let mut buffer = buffer(0);
+        let mut pcm = [0i16; 5759];
+        assert_eq!(buffer.pull(&mut pcm), Err(Error::BufferTooSmall));
+    }
+
+    #[test]
+    fn reorders_packets() {
+        let packets = encode(4, true);
+        let mut buffer = buffer(80);
+        for &i in &[1, 0, 3, 2] {
+            buffer
+                .insert(i as u16, i as u32 * 960, &packets[i])
+                .unwrap();
+        }
+        assert_eq!(play(&mut buffer, 4 * 960), [Playout::Decoded(960); 4]);
+        assert_eq!(buffer.stats().lost, 0);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn drops_duplicates_and_late_packets() {
+        let packets = encode(4, true);
+        let mut buffer = buffer(40);
+        buffer.insert(0, 0, &packets[0]).unwrap();
+        buffer.insert(0, 0, &packets[0]).unwrap();
+        buffer.insert(2, 1920, &packets[2]).unwrap();
+        play(&mut buffer, 960);
+        // Lost 1 is concealed, then it arrives too late.
+        play(&mut buffer, 960);
+        buffer.insert(1, 960, &packets[1]).unwrap();
+        buffer.insert(2, 1920, &packets[2]).unwrap();
+        play(&mut buffer, 960);
+        buffer.insert(2, 1920, &packets[2]).unwrap();
+
+        let stats = buffer.stats();
+        assert_eq!(stats.received, 2);
+        assert_eq!(stats.duplicates, 2);
+        assert_eq!(stats.late, 2);
+        assert_eq!(stats.lost, 1);
+    }
+
+    #[test]
+    fn conceals_dtx_gaps_without_loss() {
+        let packets = encode(2, true);
+        let mut buffer = buffer(20);
+        buffer.insert(0, 0, &packets[0]).unwrap();
+        // Consecutive sequence numbers, 60 ms of silence in between.
+        buffer.insert(1, 4 * 960, &packets[1]).unwrap();
+        let playouts = play(&mut buffer, 5 * 960);
+        assert_eq!(
+            playouts,
+            [
+                Playout::Decoded(960),
+                Playout::Concealed(960),
+                Playout::Concealed(960),
+                Playout::Concealed(960),
+                Playout::Decoded(960),
+            ]
+        );
+        assert_eq!(buffer.stats().lost, 0);
+        assert_eq!(buffer.stats().concealed, 3);
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_around() {
+        let packets = encode(4, true);
+        let mut buffer = buffer(40);
+        for (i, packet) in packets.iter().enumerate() {
+            let sequence = 65534u16.wrapping_add(i as u16);
+            buffer.insert(sequence, i as u32 * 960, packet).unwrap();
+        }
+        assert_eq!(play(&mut buffer, 4 * 960), [Playout::Decoded(960); 4]);
+        assert_eq!(buffer.stats().lost, 0);
+        assert_eq!(buffer.stats().late, 0);
+    }
+
+    #[test]
+    fn timestamps_wrap_around() {
+        let packets = encode(4, true);
+        let mut buffer = buffer(40);
+        for (i, packet) in packets.iter().enumerate() {
+            let timestamp = (u32::MAX - 1000).wrapping_add(i as u32 * 960);
+            buffer.insert(i as u16 + 10, timestamp, packet).unwrap();
+        }
+        assert_eq!(play(&mut buffer, 4 * 960), [Playout::Decoded(960); 4]);
+        assert_eq!(buffer.stats().late, 0);
+        assert_eq!(buffer.stats().concealed, 0);
+    }
+
+    /// Plays `packets` with packet 2 missing, returns how it was filled.
+    fn lose_one(packets: &[Vec<u8>], fec: bool) -> (Playout, JitterStats) {
+        let mut buffer = buffer(40);
+        buffer.set_fec(fec);
+        for (i, packet) in packets.iter().enumerate().filter(|&(i, _)| i != 2) {
+            buffer.insert(i as u16, i as u32 * 960, packet).unwrap();
+        }
+        let playouts = play(&mut buffer, packets.len() * 960);
+        (playouts[2], buffer.stats())
+    }
+
+    #[test]
+    fn recovers_from_fec_data() {
+        let packets = encode(4, true);
+        assert!(Packet::new(&packets[3]).unwrap().has_fec());
+        let (playout, stats) = lose_one(&packets, true);
+        assert_eq!(playout, Playout::Recovered(960));
+        assert_eq!(stats.lost, 1);
+        assert_eq!(stats.recovered, 1);
+        assert_eq!(stats.concealed, 0);
+    }
+
+    #[test]
+    fn conceals_without_fec() {
+        let packets = encode(4, true);
+        let (playout, stats) = lose_one(&packets, false);
+        assert_eq!(playout, Playout::Concealed(960));
+        assert_eq!((stats.lost, stats.recovered, stats.concealed), (1, 0, 1));
+
+        let packets = encode(4, false);
+        assert!(!Packet::new(&packets[3]).unwrap().has_fec());
+        let (playout, stats) = lose_one(&packets, true);
+        assert_eq!(playout, Playout::Concealed(960));
+        assert_eq!((stats.lost, stats.recovered, stats.concealed), (1, 0, 1));
+    }
+
+    #[test]
+    fn conceals_after_celt() {
+        // CELT, then SILK with FEC data after the loss.
+        let mut mixed = encode(2, false);
+        mixed.extend(encode(2, true));
+        assert!(Packet::new(&mixed[3]).unwrap().has_fec());
+        let (playout, stats) = lose_one(&mixed, true);
+        assert_eq!(playout, Playout::Concealed(960));
+        assert_eq!((stats.lost, stats.recovered, stats.concealed), (1, 0, 1));
+    }
+
+    #[test]
+    fn failed_recovery_skips_the_gap() {
+        let packets = encode(4, true);
+        let mut buffer = buffer(40);
+        for &i in &[0, 1, 3] {
+            buffer
+                .insert(i, u32::from(i) * 960, &packets[usize::from(i)])
+                .unwrap();
+        }
+        // Broken after the checks of `insert`: one frame, its padding length
+        // missing.
+        let toc = packets[3][0] | 3;
+        buffer.packets.get_mut(&3).unwrap().data = vec![toc, 0x40 | 1];
+
+        assert_eq!(play(&mut buffer, 2 * 960), [Playout::Decoded(960); 2]);
+        let mut pcm = [0i16; 5760];
+        assert_eq!(buffer.pull(&mut pcm), Err(Error::InvalidPacket));
+        assert_eq!(buffer.stats().lost, 1);
+        assert_eq!(buffer.stats().recovered, 0);
+        // Then the packet itself fails and is dropped.
+        assert_eq!(buffer.pull(&mut pcm), Err(Error::InvalidPacket));
+        assert!(buffer.is_empty());
+        assert_eq!(play(&mut buffer, 960), [Playout::Concealed(960)]);
+    }
+
+    #[test]
+    fn conceals_underruns() {
+        let packets = encode(1, true);
+        let mut buffer = buffer(0);
+        buffer.insert(0, 0, &packets[0]).unwrap();
+        assert_eq!(
+            play(&mut buffer, 2 * 960),
+            [Playout::Decoded(960), Playout::Concealed(960)]
+        );
+        // The packet the underrun stood in for is late, the next one plays.
+        buffer.insert(1, 960, &packets[0]).unwrap();
+        assert_eq!(buffer.stats().late, 1);
+        buffer.insert(2, 1920, &packets[0]).unwrap();
+        assert_eq!(play(&mut buffer, 960), [Playout::Decoded(960)]);
+        assert_eq!(buffer.stats().lost, 1);
+    }
+}
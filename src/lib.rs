@@ -1,5 +1,40 @@
-#![allow(non_upper_case_globals)]
-#![allow(non_camel_case_types)]
-#![allow(non_snake_case)]
+//! Opus Interactive Audio Codec bindings.
+//!
+//! [`sys`] holds the raw libopus API, re-exported at the crate root as well.
+//! [`Encoder`] and [`Decoder`] are safe owners of libopus states.
 
-include!(concat!(env!("OUT_DIR"), "/opus_bindings.rs"));
+pub mod build_options;
+mod codec;
+mod config;
+pub mod ctl;
+mod decoder;
+mod encoder;
+mod error;
+mod jitter;
+mod multistream;
+pub mod packet;
+mod params;
+mod projection;
+mod repacketizer;
+mod sample;
+mod state;
+mod stream;
+pub mod sys;
+#[cfg(test)]
+mod testing;
+mod types;
+
+pub use config::{EncoderBuilder, EncoderConfig};
+pub use decoder::Decoder;
+pub use encoder::Encoder;
+pub use error::Error;
+pub use jitter::{JitterBuffer, JitterStats, Playout};
+pub use multistream::{MultistreamDecoder, MultistreamEncoder};
+pub use packet::Packet;
+pub use projection::{ProjectionDecoder, ProjectionEncoder};
+pub use repacketizer::Repacketizer;
+pub use sample::{Sample, I24};
+pub use state::{BorrowedState, OwnedState, STATE_ALIGN};
+pub use stream::{StreamEncoder, StreamPacket};
+pub use sys::*;
+pub use types::{Application, Bandwidth, Bitrate, Channels, FrameDuration, SampleRate, Signal};
@@ -0,0 +1,410 @@
+use std::convert::TryFrom;
+use std::os::raw::c_int;
+
+use crate::codec::{Call, DecoderCore, EncoderCore};
+use crate::config::EncoderConfig;
+use crate::error::{check, Error};
+use crate::sample::Sample;
+use crate::state::OwnedState;
+use crate::sys::*;
+use crate::types::{Application, SampleRate};
+
+/// Multistream encoder, several mono and stereo Opus streams in one packet.
+///
+/// Input is interleaved PCM with [`channels`](Self::channels) channels, in
+/// any [`Sample`] format. [`streams`](Self::streams),
+/// [`coupled_streams`](Self::coupled_streams) and [`mapping`](Self::mapping)
+/// are what a container header needs to describe the stream layout.
+#[derive(Debug)]
+pub struct MultistreamEncoder {
+    core: EncoderCore<OpusMSEncoder>,
+    _memory: OwnedState,
+    mapping_family: Option<u8>,
+    streams: usize,
+    coupled_streams: usize,
+    mapping: Vec<u8>,
+}
+
+impl MultistreamEncoder {
+    /// Encoder for a standard layout of `channels` in `mapping_family`: 0 for
+    /// mono or stereo, 1 for the Vorbis orders of 1 to 8 channels, 255 for
+    /// independent mono streams. libopus picks the streams and the mapping,
+    /// and with family 1 allocates bits across the surround channels.
+    pub fn surround(
+        sample_rate: SampleRate,
+        channels: usize,
+        mapping_family: u8,
+        application: Application,
+    ) -> Result<Self, Error> {
+        let channels_raw = c_int::try_from(channels).map_err(|_| Error::BadArg)?;
+        let family = c_int::from(mapping_family);
+        let size = unsafe { opus_multistream_surround_encoder_get_size(channels_raw, family) };
+        if size <= 0 {
+            return Err(Error::BadArg);
+        }
+        let memory = OwnedState::new(size as usize);
+        let state = memory.as_state::<OpusMSEncoder>();
+        let mut streams = 0;
+        let mut coupled_streams = 0;
+        let mut mapping = vec![0; channels];
+        check(unsafe {
+            opus_multistream_surround_encoder_init(
+                state.as_ptr(),
+                sample_rate.raw(),
+                channels_raw,
+                family,
+                &mut streams,
+                &mut coupled_streams,
+                mapping.as_mut_ptr(),
+                application.raw(),
+            )
+        })?;
+
+        Ok(MultistreamEncoder {
+            core: EncoderCore::new(state, sample_rate, channels),
+            _memory: memory,
+            mapping_family: Some(mapping_family),
+            streams: streams as usize,
+            coupled_streams: coupled_streams as usize,
+            mapping,
+        })
+    }
+
+    /// Encoder with an explicit layout. The first `coupled_streams` of the
+    /// `streams` are stereo. `mapping` has one entry per input channel: the
+    /// index of the decoded channel it feeds, coupled ones first (left, right
+    /// of each), or 255 for a silent channel.
+    pub fn new(
+        sample_rate: SampleRate,
+        streams: usize,
+        coupled_streams: usize,
+        mapping: &[u8],
+        application: Application,
+    ) -> Result<Self, Error> {
+        let (streams_raw, coupled_raw, channels) = layout(streams, coupled_streams, mapping)?;
+        let size = unsafe { opus_multistream_encoder_get_size(streams_raw, coupled_raw) };
+        if size <= 0 {
+            return Err(Error::BadArg);
+        }
+        let memory = OwnedState::new(size as usize);
+        let state = memory.as_state::<OpusMSEncoder>();
+        check(unsafe {
+            opus_multistream_encoder_init(
+                state.as_ptr(),
+                sample_rate.raw(),
+                channels,
+                streams_raw,
+                coupled_raw,
+                mapping.as_ptr(),
+                application.raw(),
+            )
+        })?;
+
+        Ok(MultistreamEncoder {
+            core: EncoderCore::new(state, sample_rate, mapping.len()),
+            _memory: memory,
+            mapping_family: None,
+            streams,
+            coupled_streams,
+            mapping: mapping.to_vec(),
+        })
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.core.sample_rate()
+    }
+
+    /// Input channels.
+    pub fn channels(&self) -> usize {
+        self.mapping.len()
+    }
+
+    /// Family the encoder was created with by [`surround`](Self::surround),
+    /// `None` for an explicit layout.
+    pub fn mapping_family(&self) -> Option<u8> {
+        self.mapping_family
+    }
+
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    pub fn coupled_streams(&self) -> usize {
+        self.coupled_streams
+    }
+
+    /// Channel mapping table, one entry per input channel.
+    pub fn mapping(&self) -> &[u8] {
+        &self.mapping
+    }
+
+    /// Encodes one frame of PCM into `output`, returns the packet length.
+    pub fn encode<T: Sample>(&mut self, pcm: &[T], output: &mut [u8]) -> Result<usize, Error> {
+        self.core.encode(pcm, output)
+    }
+
+    /// Float version of [`encode`](Self::encode), input in the +/-1.0 range.
+    pub fn encode_float(&mut self, pcm: &[f32], output: &mut [u8]) -> Result<usize, Error> {
+        self.encode(pcm, output)
+    }
+
+    /// Applies every setting of `config` to all streams. Settings after the
+    /// first one libopus rejects are not applied.
+    pub fn apply_config(&mut self, config: &EncoderConfig) -> Result<(), Error> {
+        self.core.apply_config(config)
+    }
+
+    /// Effective settings, read back from libopus, those of the first stream
+    /// except for the bitrate, the total of all streams as of the last frame.
+    ///
+    /// In surround layouts of family 1 libopus forces stereo on the coupled
+    /// streams for every frame, `force_channels` is `None` there so the
+    /// result can be applied again.
+    pub fn config(&mut self) -> Result<EncoderConfig, Error> {
+        let mut config = self.core.config()?;
+        if self.mapping_family == Some(1) && self.channels() > 2 {
+            config.force_channels = None;
+        }
+        Ok(config)
+    }
+
+    /// Raw state for [`ctl::multistream_encoder`](crate::ctl::multistream_encoder)
+    /// and other raw calls. It stays owned by the encoder.
+    pub fn as_mut_ptr(&mut self) -> *mut OpusMSEncoder {
+        self.core.as_ptr()
+    }
+}
+
+/// Multistream decoder, the counterpart of [`MultistreamEncoder`].
+///
+/// Output is interleaved PCM with one channel per `mapping` entry, in any
+/// [`Sample`] format. Every call returns the number of samples per channel
+/// written to the start of `pcm`.
+#[derive(Debug)]
+pub struct MultistreamDecoder {
+    core: DecoderCore<OpusMSDecoder>,
+    _memory: OwnedState,
+    streams: usize,
+    coupled_streams: usize,
+    mapping: Vec<u8>,
+}
+
+impl MultistreamDecoder {
+    /// Decoder for the layout written by the encoder: `streams`,
+    /// `coupled_streams` and one `mapping` entry per output channel.
+    pub fn new(
+        sample_rate: SampleRate,
+        streams: usize,
+        coupled_streams: usize,
+        mapping: &[u8],
+    ) -> Result<Self, Error> {
+        let (streams_raw, coupled_raw, channels) = layout(streams, coupled_streams, mapping)?;
+        let size = unsafe { opus_multistream_decoder_get_size(streams_raw, coupled_raw) };
+        if size <= 0 {
+            return Err(Error::BadArg);
+        }
+        let memory = OwnedState::new(size as usize);
+        let state = memory.as_state::<OpusMSDecoder>();
+        check(unsafe {
+            opus_multistream_decoder_init(
+                state.as_ptr(),
+                sample_rate.raw(),
+                channels,
+                streams_raw,
+                coupled_raw,
+                mapping.as_ptr(),
+            )
+        })?;
+
+        Ok(MultistreamDecoder {
+            core: DecoderCore::new(state, sample_rate, mapping.len()),
+            _memory: memory,
+            streams,
+            coupled_streams,
+            mapping: mapping.to_vec(),
+        })
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.core.sample_rate()
+    }
+
+    /// Output channels.
+    pub fn channels(&self) -> usize {
+        self.mapping.len()
+    }
+
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    pub fn coupled_streams(&self) -> usize {
+        self.coupled_streams
+    }
+
+    /// Channel mapping table, one entry per output channel.
+    pub fn mapping(&self) -> &[u8] {
+        &self.mapping
+    }
+
+    /// Adds TPDF dither when decoding to integer formats. `i16` output is then
+    /// decoded through float too. Off by default.
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.core.set_dither(enabled);
+    }
+
+    /// Decodes `packet`, `pcm` must hold its duration for every channel or
+    /// [`Error::BufferTooSmall`] is returned.
+    pub fn decode<T: Sample>(&mut self, packet: &[u8], pcm: &mut [T]) -> Result<usize, Error> {
+        self.core.call(packet, pcm, Call::Decode)
+    }
+
+    /// Float version of [`decode`](Self::decode).
+    pub fn decode_float(&mut self, packet: &[u8], pcm: &mut [f32]) -> Result<usize, Error> {
+        self.decode(packet, pcm)
+    }
+
+    /// Packet loss concealment: synthesizes a lost frame filling all of `pcm`.
+    /// Its duration must be a multiple of 2.5 ms.
+    pub fn conceal<T: Sample>(&mut self, pcm: &mut [T]) -> Result<usize, Error> {
+        self.core.call(&[], pcm, Call::Lost { fec: false })
+    }
+
+    /// Recovers the frame lost right before `next_packet` from the in-band
+    /// FEC data of its streams, filling all of `pcm`. See
+    /// [`Decoder::decode_fec`](crate::Decoder::decode_fec).
+    pub fn decode_fec<T: Sample>(
+        &mut self,
+        next_packet: &[u8],
+        pcm: &mut [T],
+    ) -> Result<usize, Error> {
+        self.core.call(next_packet, pcm, Call::Lost { fec: true })
+    }
+
+    /// Raw state for [`ctl::multistream_decoder`](crate::ctl::multistream_decoder)
+    /// and other raw calls. It stays owned by the decoder.
+    pub fn as_mut_ptr(&mut self) -> *mut OpusMSDecoder {
+        self.core.as_ptr()
+    }
+}
+
+/// Checks an explicit stream layout, returns streams, coupled streams and
+/// channels as libopus takes them.
+fn layout(
+    streams: usize,
+    coupled_streams: usize,
+    mapping: &[u8],
+) -> Result<(c_int, c_int, c_int), Error> {
+    if streams == 0 || coupled_streams > streams || streams + coupled_streams > 255 {
+        return Err(Error::BadArg);
+    }
+    if mapping.is_empty() || mapping.len() > 255 {
+        return Err(Error::BadArg);
+    }
+    Ok((
+        streams as c_int,
+        coupled_streams as c_int,
+        mapping.len() as c_int,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{sine, FRAME};
+
+    fn surround(channels: usize, family: u8) -> Result<MultistreamEncoder, Error> {
+        MultistreamEncoder::surround(SampleRate::Hz48000, channels, family, Application::Audio)
+    }
+
+    /// Streams, coupled streams and mapping of a surround encoder.
+    fn surround_layout(channels: usize, family: u8) -> (usize, usize, Vec<u8>) {
+        let encoder = surround(channels, family).unwrap();
+        assert_eq!(encoder.channels(), channels);
+        assert_eq!(encoder.mapping_family(), Some(family));
+        let mapping = encoder.mapping().to_vec();
+        (encoder.streams(), encoder.coupled_streams(), mapping)
+    }
+
+    #[test]
+    fn surround_layouts() {
+        assert_eq!(surround_layout(1, 0), (1, 0, vec![0]));
+        assert_eq!(surround_layout(2, 0), (1, 1, vec![0, 1]));
+        assert_eq!(surround_layout(3, 1), (2, 1, vec![0, 2, 1]));
+        assert_eq!(surround_layout(6, 1), (4, 2, vec![0, 4, 1, 2, 3, 5]));
+        assert_eq!(surround_layout(8, 1), (5, 3, vec![0, 6, 1, 2, 3, 4, 5, 7]));
+        assert_eq!(surround_layout(3, 255), (3, 0, vec![0, 1, 2]));
+
+        assert_eq!(surround(3, 0).err(), Some(Error::BadArg));
+        assert_eq!(surround(9, 1).err(), Some(Error::BadArg));
+        assert_eq!(surround(0, 255).err(), Some(Error::BadArg));
+        assert_eq!(surround(2, 7).err(), Some(Error::BadArg));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut encoder = surround(6, 1).unwrap();
+        let mut decoder = MultistreamDecoder::new(
+            SampleRate::Hz48000,
+            encoder.streams(),
+            encoder.coupled_streams(),
+            encoder.mapping(),
+        )
+        .unwrap();
+        assert_eq!(decoder.channels(), 6);
+
+        let mut packet = [0; 4000];
+        let mut pcm = vec![0i16; FRAME * 6];
+        for index in 0..5 {
+            let len = encoder.encode(&sine(index, 6), &mut packet).unwrap();
+            assert_eq!(decoder.decode(&packet[..len], &mut pcm).unwrap(), FRAME);
+        }
+        // Past the codec delay every channel carries the sine, except the LFE
+        // channel, the last one, which only keeps low frequencies.
+        for channel in 0..5 {
+            let peak = pcm.iter().skip(channel).step_by(6).map(|s| s.abs()).max();
+            assert!(peak.unwrap() > 4000, "channel {}", channel);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_layouts() {
+        let encoder = |streams, coupled_streams, mapping: &[u8]| {
+            MultistreamEncoder::new(
+                SampleRate::Hz48000,
+                streams,
+                coupled_streams,
+                mapping,
+                Application::Audio,
+            )
+            .err()
+        };
+        let decoder = |streams, coupled_streams, mapping: &[u8]| {
+            MultistreamDecoder::new(SampleRate::Hz48000, streams, coupled_streams, mapping).err()
+        };
+
+        let invalid: &[(usize, usize, &[u8])] = &[
+            (0, 0, &[0]),
+            (1, 2, &[0, 1, 2]),
+            (200, 100, &[0]),
+            (1, 0, &[]),
+            (1, 0, &[0; 256]),
+            // Entries past the 3 decoded channels of the streams.
+            (2, 1, &[0, 1, 3]),
+        ];
+        for &(streams, coupled_streams, mapping) in invalid {
+            assert_eq!(
+                encoder(streams, coupled_streams, mapping),
+                Some(Error::BadArg)
+            );
+            assert_eq!(
+                decoder(streams, coupled_streams, mapping),
+                Some(Error::BadArg)
+            );
+        }
+
+        // A silent output channel.
+        assert_eq!(decoder(1, 0, &[0, 255]), None);
+        assert_eq!(encoder(2, 1, &[0, 1, 2]), None);
+    }
+}
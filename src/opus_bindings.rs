@@ -0,0 +1,587 @@
+/* automatically generated by rust-bindgen */
+
+pub const OPUS_OK: u32 = 0;
+pub const OPUS_BAD_ARG: i32 = -1;
+pub const OPUS_BUFFER_TOO_SMALL: i32 = -2;
+pub const OPUS_INTERNAL_ERROR: i32 = -3;
+pub const OPUS_INVALID_PACKET: i32 = -4;
+pub const OPUS_UNIMPLEMENTED: i32 = -5;
+pub const OPUS_INVALID_STATE: i32 = -6;
+pub const OPUS_ALLOC_FAIL: i32 = -7;
+pub const OPUS_SET_APPLICATION_REQUEST: u32 = 4000;
+pub const OPUS_GET_APPLICATION_REQUEST: u32 = 4001;
+pub const OPUS_SET_BITRATE_REQUEST: u32 = 4002;
+pub const OPUS_GET_BITRATE_REQUEST: u32 = 4003;
+pub const OPUS_SET_MAX_BANDWIDTH_REQUEST: u32 = 4004;
+pub const OPUS_GET_MAX_BANDWIDTH_REQUEST: u32 = 4005;
+pub const OPUS_SET_VBR_REQUEST: u32 = 4006;
+pub const OPUS_GET_VBR_REQUEST: u32 = 4007;
+pub const OPUS_SET_BANDWIDTH_REQUEST: u32 = 4008;
+pub const OPUS_GET_BANDWIDTH_REQUEST: u32 = 4009;
+pub const OPUS_SET_COMPLEXITY_REQUEST: u32 = 4010;
+pub const OPUS_GET_COMPLEXITY_REQUEST: u32 = 4011;
+pub const OPUS_SET_INBAND_FEC_REQUEST: u32 = 4012;
+pub const OPUS_GET_INBAND_FEC_REQUEST: u32 = 4013;
+pub const OPUS_SET_PACKET_LOSS_PERC_REQUEST: u32 = 4014;
+pub const OPUS_GET_PACKET_LOSS_PERC_REQUEST: u32 = 4015;
+pub const OPUS_SET_DTX_REQUEST: u32 = 4016;
+pub const OPUS_GET_DTX_REQUEST: u32 = 4017;
+pub const OPUS_SET_VBR_CONSTRAINT_REQUEST: u32 = 4020;
+pub const OPUS_GET_VBR_CONSTRAINT_REQUEST: u32 = 4021;
+pub const OPUS_SET_FORCE_CHANNELS_REQUEST: u32 = 4022;
+pub const OPUS_GET_FORCE_CHANNELS_REQUEST: u32 = 4023;
+pub const OPUS_SET_SIGNAL_REQUEST: u32 = 4024;
+pub const OPUS_GET_SIGNAL_REQUEST: u32 = 4025;
+pub const OPUS_GET_LOOKAHEAD_REQUEST: u32 = 4027;
+pub const OPUS_GET_SAMPLE_RATE_REQUEST: u32 = 4029;
+pub const OPUS_GET_FINAL_RANGE_REQUEST: u32 = 4031;
+pub const OPUS_GET_PITCH_REQUEST: u32 = 4033;
+pub const OPUS_SET_GAIN_REQUEST: u32 = 4034;
+pub const OPUS_GET_GAIN_REQUEST: u32 = 4045;
+pub const OPUS_SET_LSB_DEPTH_REQUEST: u32 = 4036;
+pub const OPUS_GET_LSB_DEPTH_REQUEST: u32 = 4037;
+pub const OPUS_GET_LAST_PACKET_DURATION_REQUEST: u32 = 4039;
+pub const OPUS_SET_EXPERT_FRAME_DURATION_REQUEST: u32 = 4040;
+pub const OPUS_GET_EXPERT_FRAME_DURATION_REQUEST: u32 = 4041;
+pub const OPUS_SET_PREDICTION_DISABLED_REQUEST: u32 = 4042;
+pub const OPUS_GET_PREDICTION_DISABLED_REQUEST: u32 = 4043;
+pub const OPUS_SET_PHASE_INVERSION_DISABLED_REQUEST: u32 = 4046;
+pub const OPUS_GET_PHASE_INVERSION_DISABLED_REQUEST: u32 = 4047;
+pub const OPUS_AUTO: i32 = -1000;
+pub const OPUS_BITRATE_MAX: i32 = -1;
+pub const OPUS_APPLICATION_VOIP: u32 = 2048;
+pub const OPUS_APPLICATION_AUDIO: u32 = 2049;
+pub const OPUS_APPLICATION_RESTRICTED_LOWDELAY: u32 = 2051;
+pub const OPUS_SIGNAL_VOICE: u32 = 3001;
+pub const OPUS_SIGNAL_MUSIC: u32 = 3002;
+pub const OPUS_BANDWIDTH_NARROWBAND: u32 = 1101;
+pub const OPUS_BANDWIDTH_MEDIUMBAND: u32 = 1102;
+pub const OPUS_BANDWIDTH_WIDEBAND: u32 = 1103;
+pub const OPUS_BANDWIDTH_SUPERWIDEBAND: u32 = 1104;
+pub const OPUS_BANDWIDTH_FULLBAND: u32 = 1105;
+pub const OPUS_FRAMESIZE_ARG: u32 = 5000;
+pub const OPUS_FRAMESIZE_2_5_MS: u32 = 5001;
+pub const OPUS_FRAMESIZE_5_MS: u32 = 5002;
+pub const OPUS_FRAMESIZE_10_MS: u32 = 5003;
+pub const OPUS_FRAMESIZE_20_MS: u32 = 5004;
+pub const OPUS_FRAMESIZE_40_MS: u32 = 5005;
+pub const OPUS_FRAMESIZE_60_MS: u32 = 5006;
+pub const OPUS_FRAMESIZE_80_MS: u32 = 5007;
+pub const OPUS_FRAMESIZE_100_MS: u32 = 5008;
+pub const OPUS_FRAMESIZE_120_MS: u32 = 5009;
+pub const OPUS_RESET_STATE: u32 = 4028;
+pub const OPUS_MULTISTREAM_GET_ENCODER_STATE_REQUEST: u32 = 5120;
+pub const OPUS_MULTISTREAM_GET_DECODER_STATE_REQUEST: u32 = 5122;
+pub const OPUS_PROJECTION_GET_DEMIXING_MATRIX_GAIN_REQUEST: u32 = 6001;
+pub const OPUS_PROJECTION_GET_DEMIXING_MATRIX_SIZE_REQUEST: u32 = 6003;
+pub const OPUS_PROJECTION_GET_DEMIXING_MATRIX_REQUEST: u32 = 6005;
+pub type opus_int32 = i32;
+pub type opus_int16 = i16;
+extern "C" {
+    pub fn opus_strerror(error: ::std::os::raw::c_int) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn opus_get_version_string() -> *const ::std::os::raw::c_char;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusEncoder {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn opus_encoder_get_size(channels: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_encoder_create(
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        application: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusEncoder;
+}
+extern "C" {
+    pub fn opus_encoder_init(
+        st: *mut OpusEncoder,
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        application: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_encode(
+        st: *mut OpusEncoder,
+        pcm: *const opus_int16,
+        frame_size: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_encode_float(
+        st: *mut OpusEncoder,
+        pcm: *const f32,
+        frame_size: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_encoder_destroy(st: *mut OpusEncoder);
+}
+extern "C" {
+    pub fn opus_encoder_ctl(
+        st: *mut OpusEncoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusDecoder {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn opus_decoder_get_size(channels: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_decoder_create(
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusDecoder;
+}
+extern "C" {
+    pub fn opus_decoder_init(
+        st: *mut OpusDecoder,
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_decode(
+        st: *mut OpusDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        pcm: *mut opus_int16,
+        frame_size: ::std::os::raw::c_int,
+        decode_fec: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_decode_float(
+        st: *mut OpusDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        pcm: *mut f32,
+        frame_size: ::std::os::raw::c_int,
+        decode_fec: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_decoder_ctl(
+        st: *mut OpusDecoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_decoder_destroy(st: *mut OpusDecoder);
+}
+extern "C" {
+    pub fn opus_packet_parse(
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        out_toc: *mut ::std::os::raw::c_uchar,
+        frames: *mut *const ::std::os::raw::c_uchar,
+        size: *mut opus_int16,
+        payload_offset: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_packet_get_bandwidth(data: *const ::std::os::raw::c_uchar)
+        -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_packet_get_samples_per_frame(
+        data: *const ::std::os::raw::c_uchar,
+        Fs: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_packet_get_nb_channels(
+        data: *const ::std::os::raw::c_uchar,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_packet_get_nb_frames(
+        packet: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_packet_get_nb_samples(
+        packet: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        Fs: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_decoder_get_nb_samples(
+        dec: *const OpusDecoder,
+        packet: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_pcm_soft_clip(
+        pcm: *mut f32,
+        frame_size: ::std::os::raw::c_int,
+        channels: ::std::os::raw::c_int,
+        softclip_mem: *mut f32,
+    );
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusRepacketizer {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn opus_repacketizer_get_size() -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_repacketizer_init(rp: *mut OpusRepacketizer) -> *mut OpusRepacketizer;
+}
+extern "C" {
+    pub fn opus_repacketizer_create() -> *mut OpusRepacketizer;
+}
+extern "C" {
+    pub fn opus_repacketizer_destroy(rp: *mut OpusRepacketizer);
+}
+extern "C" {
+    pub fn opus_repacketizer_cat(
+        rp: *mut OpusRepacketizer,
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_repacketizer_out_range(
+        rp: *mut OpusRepacketizer,
+        begin: ::std::os::raw::c_int,
+        end: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_uchar,
+        maxlen: opus_int32,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_repacketizer_get_nb_frames(rp: *mut OpusRepacketizer) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_repacketizer_out(
+        rp: *mut OpusRepacketizer,
+        data: *mut ::std::os::raw::c_uchar,
+        maxlen: opus_int32,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_packet_pad(
+        data: *mut ::std::os::raw::c_uchar,
+        len: opus_int32,
+        new_len: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_packet_unpad(data: *mut ::std::os::raw::c_uchar, len: opus_int32) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_multistream_packet_pad(
+        data: *mut ::std::os::raw::c_uchar,
+        len: opus_int32,
+        new_len: opus_int32,
+        nb_streams: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_packet_unpad(
+        data: *mut ::std::os::raw::c_uchar,
+        len: opus_int32,
+        nb_streams: ::std::os::raw::c_int,
+    ) -> opus_int32;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusMSEncoder {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusMSDecoder {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn opus_multistream_encoder_get_size(
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_multistream_surround_encoder_get_size(
+        channels: ::std::os::raw::c_int,
+        mapping_family: ::std::os::raw::c_int,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_multistream_encoder_create(
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+        mapping: *const ::std::os::raw::c_uchar,
+        application: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusMSEncoder;
+}
+extern "C" {
+    pub fn opus_multistream_surround_encoder_create(
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        mapping_family: ::std::os::raw::c_int,
+        streams: *mut ::std::os::raw::c_int,
+        coupled_streams: *mut ::std::os::raw::c_int,
+        mapping: *mut ::std::os::raw::c_uchar,
+        application: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusMSEncoder;
+}
+extern "C" {
+    pub fn opus_multistream_encoder_init(
+        st: *mut OpusMSEncoder,
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+        mapping: *const ::std::os::raw::c_uchar,
+        application: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_surround_encoder_init(
+        st: *mut OpusMSEncoder,
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        mapping_family: ::std::os::raw::c_int,
+        streams: *mut ::std::os::raw::c_int,
+        coupled_streams: *mut ::std::os::raw::c_int,
+        mapping: *mut ::std::os::raw::c_uchar,
+        application: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_encode(
+        st: *mut OpusMSEncoder,
+        pcm: *const opus_int16,
+        frame_size: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_encode_float(
+        st: *mut OpusMSEncoder,
+        pcm: *const f32,
+        frame_size: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_encoder_destroy(st: *mut OpusMSEncoder);
+}
+extern "C" {
+    pub fn opus_multistream_encoder_ctl(
+        st: *mut OpusMSEncoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_decoder_get_size(
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_multistream_decoder_create(
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+        mapping: *const ::std::os::raw::c_uchar,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusMSDecoder;
+}
+extern "C" {
+    pub fn opus_multistream_decoder_init(
+        st: *mut OpusMSDecoder,
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+        mapping: *const ::std::os::raw::c_uchar,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_decode(
+        st: *mut OpusMSDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        pcm: *mut opus_int16,
+        frame_size: ::std::os::raw::c_int,
+        decode_fec: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_decode_float(
+        st: *mut OpusMSDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        pcm: *mut f32,
+        frame_size: ::std::os::raw::c_int,
+        decode_fec: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_decoder_ctl(
+        st: *mut OpusMSDecoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_multistream_decoder_destroy(st: *mut OpusMSDecoder);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusProjectionEncoder {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusProjectionDecoder {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn opus_projection_ambisonics_encoder_get_size(
+        channels: ::std::os::raw::c_int,
+        mapping_family: ::std::os::raw::c_int,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_projection_ambisonics_encoder_create(
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        mapping_family: ::std::os::raw::c_int,
+        streams: *mut ::std::os::raw::c_int,
+        coupled_streams: *mut ::std::os::raw::c_int,
+        application: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusProjectionEncoder;
+}
+extern "C" {
+    pub fn opus_projection_ambisonics_encoder_init(
+        st: *mut OpusProjectionEncoder,
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        mapping_family: ::std::os::raw::c_int,
+        streams: *mut ::std::os::raw::c_int,
+        coupled_streams: *mut ::std::os::raw::c_int,
+        application: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_encode(
+        st: *mut OpusProjectionEncoder,
+        pcm: *const opus_int16,
+        frame_size: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_encode_float(
+        st: *mut OpusProjectionEncoder,
+        pcm: *const f32,
+        frame_size: ::std::os::raw::c_int,
+        data: *mut ::std::os::raw::c_uchar,
+        max_data_bytes: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_encoder_destroy(st: *mut OpusProjectionEncoder);
+}
+extern "C" {
+    pub fn opus_projection_encoder_ctl(
+        st: *mut OpusProjectionEncoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_decoder_get_size(
+        channels: ::std::os::raw::c_int,
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+    ) -> opus_int32;
+}
+extern "C" {
+    pub fn opus_projection_decoder_create(
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+        demixing_matrix: *mut ::std::os::raw::c_uchar,
+        demixing_matrix_size: opus_int32,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusProjectionDecoder;
+}
+extern "C" {
+    pub fn opus_projection_decoder_init(
+        st: *mut OpusProjectionDecoder,
+        Fs: opus_int32,
+        channels: ::std::os::raw::c_int,
+        streams: ::std::os::raw::c_int,
+        coupled_streams: ::std::os::raw::c_int,
+        demixing_matrix: *mut ::std::os::raw::c_uchar,
+        demixing_matrix_size: opus_int32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_decode(
+        st: *mut OpusProjectionDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        pcm: *mut opus_int16,
+        frame_size: ::std::os::raw::c_int,
+        decode_fec: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_decode_float(
+        st: *mut OpusProjectionDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: opus_int32,
+        pcm: *mut f32,
+        frame_size: ::std::os::raw::c_int,
+        decode_fec: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_decoder_ctl(
+        st: *mut OpusProjectionDecoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_projection_decoder_destroy(st: *mut OpusProjectionDecoder);
+}
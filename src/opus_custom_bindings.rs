@@ -0,0 +1,123 @@
+/* automatically generated by rust-bindgen */
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusCustomEncoder {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusCustomDecoder {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OpusCustomMode {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn opus_custom_mode_create(
+        Fs: opus_int32,
+        frame_size: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusCustomMode;
+}
+extern "C" {
+    pub fn opus_custom_mode_destroy(mode: *mut OpusCustomMode);
+}
+extern "C" {
+    pub fn opus_custom_encoder_get_size(
+        mode: *const OpusCustomMode,
+        channels: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_encoder_init(
+        st: *mut OpusCustomEncoder,
+        mode: *const OpusCustomMode,
+        channels: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_encoder_create(
+        mode: *const OpusCustomMode,
+        channels: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusCustomEncoder;
+}
+extern "C" {
+    pub fn opus_custom_encoder_destroy(st: *mut OpusCustomEncoder);
+}
+extern "C" {
+    pub fn opus_custom_encode_float(
+        st: *mut OpusCustomEncoder,
+        pcm: *const f32,
+        frame_size: ::std::os::raw::c_int,
+        compressed: *mut ::std::os::raw::c_uchar,
+        maxCompressedBytes: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_encode(
+        st: *mut OpusCustomEncoder,
+        pcm: *const opus_int16,
+        frame_size: ::std::os::raw::c_int,
+        compressed: *mut ::std::os::raw::c_uchar,
+        maxCompressedBytes: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_encoder_ctl(
+        st: *mut OpusCustomEncoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_decoder_get_size(
+        mode: *const OpusCustomMode,
+        channels: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_decoder_init(
+        st: *mut OpusCustomDecoder,
+        mode: *const OpusCustomMode,
+        channels: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_decoder_create(
+        mode: *const OpusCustomMode,
+        channels: ::std::os::raw::c_int,
+        error: *mut ::std::os::raw::c_int,
+    ) -> *mut OpusCustomDecoder;
+}
+extern "C" {
+    pub fn opus_custom_decoder_destroy(st: *mut OpusCustomDecoder);
+}
+extern "C" {
+    pub fn opus_custom_decode_float(
+        st: *mut OpusCustomDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: ::std::os::raw::c_int,
+        pcm: *mut f32,
+        frame_size: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_decode(
+        st: *mut OpusCustomDecoder,
+        data: *const ::std::os::raw::c_uchar,
+        len: ::std::os::raw::c_int,
+        pcm: *mut opus_int16,
+        frame_size: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn opus_custom_decoder_ctl(
+        st: *mut OpusCustomDecoder,
+        request: ::std::os::raw::c_int,
+        ...
+    ) -> ::std::os::raw::c_int;
+}
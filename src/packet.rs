@@ -0,0 +1,706 @@
+//! Packet inspection without decoding.
+//!
+//! [`Packet`] parses the TOC byte and framing (RFC 6716 §3) in Rust and
+//! rejects malformed packets with the [`Violation`] of the §3.4 requirements
+//! they break. The free functions wrap the equivalent `opus_packet_*` calls.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::os::raw::c_int;
+use std::ptr;
+
+use crate::error::{check, Error};
+use crate::params;
+use crate::sys::*;
+use crate::types::{Bandwidth, Channels, FrameDuration, SampleRate};
+
+/// Most frames in a packet, 120 ms of 2.5 ms frames.
+pub const MAX_FRAMES: usize = 48;
+
+/// Largest frame in bytes.
+pub const MAX_FRAME_SIZE: usize = 1275;
+
+/// Coding mode of a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Linear prediction, for speech.
+    Silk,
+    /// SILK below 8 kHz, CELT above.
+    Hybrid,
+    /// MDCT, for music and low delay.
+    Celt,
+}
+
+/// How frames are laid out after the TOC byte, the code in its low 2 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Framing {
+    /// Code 0, one frame.
+    Single,
+    /// Code 1, two frames of equal size.
+    TwoEqual,
+    /// Code 2, two frames of different sizes.
+    TwoDifferent,
+    /// Code 3, any number of frames, of equal size unless `vbr`.
+    Arbitrary { vbr: bool },
+}
+
+/// RFC 6716 §3.4 requirement broken by a malformed packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Violation {
+    /// R1: packets are at least one byte.
+    R1,
+    /// R2: no implicit frame length is larger than 1275 bytes.
+    R2,
+    /// R3: code 1 packets have an odd total length.
+    R3,
+    /// R4: code 2 packets have enough bytes for the first frame length, and
+    /// that length is no larger than the bytes remaining.
+    R4,
+    /// R5: code 3 packets contain at least one frame and at most 120 ms of audio.
+    R5,
+    /// R6: CBR code 3 packets have the frame count byte, padding that fits,
+    /// and a payload that divides evenly between the frames.
+    R6,
+    /// R7: VBR code 3 packets hold all their header bytes, the first M-1
+    /// frames and the padding.
+    R7,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Violation::R1 => "R1: empty packet",
+            Violation::R2 => "R2: implicit frame length larger than 1275 bytes",
+            Violation::R3 => "R3: code 1 packet of even length",
+            Violation::R4 => "R4: code 2 frame length missing or too large",
+            Violation::R5 => "R5: code 3 packet without frames or longer than 120 ms",
+            Violation::R6 => "R6: CBR code 3 packet too short or not evenly divided",
+            Violation::R7 => "R7: VBR code 3 packet too short for its header and frames",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for Violation {}
+
+impl From<Violation> for Error {
+    fn from(_: Violation) -> Error {
+        Error::InvalidPacket
+    }
+}
+
+/// The TOC byte starting every packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Toc(pub u8);
+
+impl Toc {
+    /// Configuration number, 0 to 31.
+    pub fn config(self) -> u8 {
+        self.0 >> 3
+    }
+
+    pub fn mode(self) -> Mode {
+        match self.config() {
+            0..=11 => Mode::Silk,
+            12..=15 => Mode::Hybrid,
+            _ => Mode::Celt,
+        }
+    }
+
+    pub fn bandwidth(self) -> Bandwidth {
+        match self.config() {
+            0..=3 | 16..=19 => Bandwidth::Narrowband,
+            4..=7 => Bandwidth::Mediumband,
+            8..=11 | 20..=23 => Bandwidth::Wideband,
+            12..=13 | 24..=27 => Bandwidth::Superwideband,
+            _ => Bandwidth::Fullband,
+        }
+    }
+
+    /// Duration of each frame.
+    pub fn frame_duration(self) -> FrameDuration {
+        use FrameDuration::*;
+
+        let config = self.config();
+        match self.mode() {
+            Mode::Silk => [Ms10, Ms20, Ms40, Ms60][usize::from(config & 3)],
+            Mode::Hybrid => [Ms10, Ms20][usize::from(config & 1)],
+            Mode::Celt => [Ms2_5, Ms5, Ms10, Ms20][usize::from(config & 3)],
+        }
+    }
+
+    pub fn stereo(self) -> bool {
+        self.0 & 0x4 != 0
+    }
+
+    /// Frame count code, 0 to 3.
+    pub fn code(self) -> u8 {
+        self.0 & 0x3
+    }
+}
+
+/// A validated packet, borrowed.
+#[derive(Debug, Clone, Copy)]
+pub struct Packet<'a> {
+    data: &'a [u8],
+    framing: Framing,
+    count: usize,
+    sizes: [u16; MAX_FRAMES],
+    payload_offset: usize,
+    padding: usize,
+}
+
+impl<'a> Packet<'a> {
+    /// Parses `data` as one packet, not self-delimited.
+    pub fn new(data: &'a [u8]) -> Result<Self, Violation> {
+        let (&toc, rest) = data.split_first().ok_or(Violation::R1)?;
+        let toc = Toc(toc);
+        let mut packet = Packet {
+            data,
+            framing: Framing::Single,
+            count: 1,
+            sizes: [0; MAX_FRAMES],
+            payload_offset: 1,
+            padding: 0,
+        };
+
+        match toc.code() {
+            0 => packet.sizes[0] = implicit_size(rest.len())?,
+            1 => {
+                if rest.len() % 2 != 0 {
+                    return Err(Violation::R3);
+                }
+                packet.framing = Framing::TwoEqual;
+                packet.count = 2;
+                let size = implicit_size(rest.len() / 2)?;
+                packet.sizes[..2].copy_from_slice(&[size, size]);
+            }
+            2 => {
+                let (first, used) = frame_size(rest).ok_or(Violation::R4)?;
+                let remaining = rest.len() - used;
+                if first > remaining {
+                    return Err(Violation::R4);
+                }
+                packet.framing = Framing::TwoDifferent;
+                packet.count = 2;
+                packet.payload_offset += used;
+                packet.sizes[0] = first as u16;
+                packet.sizes[1] = implicit_size(remaining - first)?;
+            }
+            _ => packet.parse_code3(toc, rest)?,
+        }
+        Ok(packet)
+    }
+
+    fn parse_code3(&mut self, toc: Toc, rest: &[u8]) -> Result<(), Violation> {
+        let (&header, mut rest) = rest.split_first().ok_or(Violation::R6)?;
+        let vbr = header & 0x80 != 0;
+        let short = if vbr { Violation::R7 } else { Violation::R6 };
+        let count = usize::from(header & 0x3f);
+        let duration = toc.frame_duration().micros() as usize;
+        if count == 0 || count * duration > 120_000 {
+            return Err(Violation::R5);
+        }
+        self.framing = Framing::Arbitrary { vbr };
+        self.count = count;
+        self.payload_offset += 1;
+
+        if header & 0x40 != 0 {
+            loop {
+                let (&byte, tail) = rest.split_first().ok_or(short)?;
+                rest = tail;
+                self.payload_offset += 1;
+                if byte == 255 {
+                    self.padding += 254;
+                } else {
+                    self.padding += usize::from(byte);
+                    break;
+                }
+            }
+        }
+        let mut remaining = rest.len().checked_sub(self.padding).ok_or(short)?;
+
+        if vbr {
+            for i in 0..count - 1 {
+                let (size, used) = frame_size(rest).ok_or(Violation::R7)?;
+                rest = &rest[used..];
+                self.payload_offset += used;
+                remaining = remaining.checked_sub(used + size).ok_or(Violation::R7)?;
+                self.sizes[i] = size as u16;
+            }
+            self.sizes[count - 1] = implicit_size(remaining)?;
+        } else {
+            if remaining % count != 0 {
+                return Err(Violation::R6);
+            }
+            let size = implicit_size(remaining / count)?;
+            self.sizes[..count].iter_mut().for_each(|s| *s = size);
+        }
+        Ok(())
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn toc(&self) -> Toc {
+        Toc(self.data[0])
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.toc().mode()
+    }
+
+    pub fn bandwidth(&self) -> Bandwidth {
+        self.toc().bandwidth()
+    }
+
+    pub fn frame_duration(&self) -> FrameDuration {
+        self.toc().frame_duration()
+    }
+
+    pub fn channels(&self) -> Channels {
+        if self.toc().stereo() {
+            Channels::Stereo
+        } else {
+            Channels::Mono
+        }
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.count
+    }
+
+    /// Size of each frame in bytes, 0 for DTX or lost frames.
+    pub fn frame_sizes(&self) -> &[u16] {
+        &self.sizes[..self.count]
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        let data = self.data;
+        let mut offset = self.payload_offset;
+        self.frame_sizes().iter().map(move |&size| {
+            let frame = &data[offset..offset + usize::from(size)];
+            offset += usize::from(size);
+            frame
+        })
+    }
+
+    /// Bytes before the first frame: TOC, frame count and padding and frame
+    /// length bytes.
+    pub fn payload_offset(&self) -> usize {
+        self.payload_offset
+    }
+
+    /// Trailing padding bytes.
+    pub fn padding(&self) -> usize {
+        self.padding
+    }
+
+    /// Whether the first frame carries in-band FEC (SILK LBRR) data for the
+    /// packet before it. Always false in CELT mode.
+    pub fn has_fec(&self) -> bool {
+        let first = match self.frames().next() {
+            Some(&[first, ..]) if self.mode() != Mode::Celt => first,
+            _ => return false,
+        };
+        // The VAD flags of each 20 ms SILK frame, then the LBRR flag, are the
+        // first bits of the range coder at even odds, so they are the top bits
+        // of the first byte. The side channel follows with its own.
+        let silk_frames = (self.samples_per_frame(SampleRate::Hz48000) / 960).max(1) as u32;
+        let mid = (first >> (7 - silk_frames)) & 1 != 0;
+        let side = self.toc().stereo() && (first >> (6 - 2 * silk_frames)) & 1 != 0;
+        mid || side
+    }
+
+    /// Samples per channel of each frame at `sample_rate`.
+    pub fn samples_per_frame(&self, sample_rate: SampleRate) -> usize {
+        self.frame_duration().samples(sample_rate)
+    }
+
+    /// Samples per channel of the whole packet at `sample_rate`.
+    pub fn nb_samples(&self, sample_rate: SampleRate) -> usize {
+        self.count * self.samples_per_frame(sample_rate)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Packet<'a> {
+    type Error = Violation;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Violation> {
+        Packet::new(data)
+    }
+}
+
+/// Checks a frame size the framing leaves implicit.
+fn implicit_size(size: usize) -> Result<u16, Violation> {
+    if size > MAX_FRAME_SIZE {
+        Err(Violation::R2)
+    } else {
+        Ok(size as u16)
+    }
+}
+
+/// Reads a one or two byte frame length, returns it with the bytes used.
+fn frame_size(data: &[u8]) -> Option<(usize, usize)> {
+    match *data {
+        [first, ..] if first < 252 => Some((usize::from(first), 1)),
+        [first, second, ..] => Some((usize::from(second) * 4 + usize::from(first), 2)),
+        _ => None,
+    }
+}
+
+/// Frames found by [`parse`].
+#[derive(Debug, Clone)]
+pub struct Parsed<'a> {
+    pub toc: Toc,
+    pub frames: Vec<&'a [u8]>,
+    pub payload_offset: usize,
+}
+
+fn first_byte(packet: &[u8]) -> Result<*const u8, Error> {
+    if packet.is_empty() {
+        Err(Error::BadArg)
+    } else {
+        Ok(packet.as_ptr())
+    }
+}
+
+/// Bandwidth of `packet`, [`opus_packet_get_bandwidth`].
+pub fn bandwidth(packet: &[u8]) -> Result<Bandwidth, Error> {
+    let raw = unsafe { opus_packet_get_bandwidth(first_byte(packet)?) };
+    Bandwidth::try_from(check(raw)?)
+}
+
+/// Channels coded in `packet`, [`opus_packet_get_nb_channels`].
+pub fn channels(packet: &[u8]) -> Result<Channels, Error> {
+    let raw = unsafe { opus_packet_get_nb_channels(first_byte(packet)?) };
+    Channels::try_from(check(raw)?)
+}
+
+/// Frames in `packet`, [`opus_packet_get_nb_frames`].
+pub fn nb_frames(packet: &[u8]) -> Result<usize, Error> {
+    let len = params::packet_len(packet)?;
+    let frames = unsafe { opus_packet_get_nb_frames(packet.as_ptr(), len) };
+    Ok(check(frames)? as usize)
+}
+
+/// Samples per channel of `packet`, [`opus_packet_get_nb_samples`].
+pub fn nb_samples(packet: &[u8], sample_rate: SampleRate) -> Result<usize, Error> {
+    let len = params::packet_len(packet)?;
+    let samples = unsafe { opus_packet_get_nb_samples(packet.as_ptr(), len, sample_rate.raw()) };
+    Ok(check(samples)? as usize)
+}
+
+/// Samples per channel of each frame of `packet`,
+/// [`opus_packet_get_samples_per_frame`].
+pub fn samples_per_frame(packet: &[u8], sample_rate: SampleRate) -> Result<usize, Error> {
+    let data = first_byte(packet)?;
+    let samples = unsafe { opus_packet_get_samples_per_frame(data, sample_rate.raw()) };
+    Ok(check(samples)? as usize)
+}
+
+/// Splits `packet` into frames, [`opus_packet_parse`].
+pub fn parse(packet: &[u8]) -> Result<Parsed<'_>, Error> {
+    let len = params::packet_len(packet)?;
+    let mut toc = 0;
+    let mut frames = [ptr::null(); MAX_FRAMES];
+    let mut sizes = [0; MAX_FRAMES];
+    let mut payload_offset: c_int = 0;
+    let count = unsafe {
+        opus_packet_parse(
+            packet.as_ptr(),
+            len,
+            &mut toc,
+            frames.as_mut_ptr(),
+            sizes.as_mut_ptr(),
+            &mut payload_offset,
+        )
+    };
+    let count = check(count)? as usize;
+
+    let base = packet.as_ptr() as usize;
+    let frames = frames[..count]
+        .iter()
+        .zip(&sizes[..count])
+        .map(|(&frame, &size)| {
+            let offset = frame as usize - base;
+            &packet[offset..offset + size as usize]
+        })
+        .collect();
+    Ok(Parsed {
+        toc: Toc(toc),
+        frames,
+        payload_offset: payload_offset as usize,
+    })
+}
+
+/// Pads the packet in `buf[..len]` to the whole of `buf`, [`opus_packet_pad`].
+/// The padded packet decodes to the same audio.
+pub fn pad(buf: &mut [u8], len: usize) -> Result<(), Error> {
+    let (len, new_len) = pad_lens(buf, len)?;
+    check(unsafe { opus_packet_pad(buf.as_mut_ptr(), len, new_len) })?;
+    Ok(())
+}
+
+/// Removes all padding from `packet` in place, returns its new length,
+/// [`opus_packet_unpad`].
+pub fn unpad(packet: &mut [u8]) -> Result<usize, Error> {
+    let len = params::packet_len(packet)?;
+    let len = unsafe { opus_packet_unpad(packet.as_mut_ptr(), len) };
+    Ok(check(len)? as usize)
+}
+
+/// [`pad`] for a multistream packet of `streams` streams,
+/// [`opus_multistream_packet_pad`]. The padding goes to the last stream.
+pub fn multistream_pad(buf: &mut [u8], len: usize, streams: usize) -> Result<(), Error> {
+    check_streams(streams)?;
+    let (len, new_len) = pad_lens(buf, len)?;
+    let ret =
+        unsafe { opus_multistream_packet_pad(buf.as_mut_ptr(), len, new_len, streams as c_int) };
+    check(ret)?;
+    Ok(())
+}
+
+/// [`unpad`] for a multistream packet of `streams` streams,
+/// [`opus_multistream_packet_unpad`].
+pub fn multistream_unpad(packet: &mut [u8], streams: usize) -> Result<usize, Error> {
+    check_streams(streams)?;
+    let len = params::packet_len(packet)?;
+    let len = unsafe { opus_multistream_packet_unpad(packet.as_mut_ptr(), len, streams as c_int) };
+    Ok(check(len)? as usize)
+}
+
+/// A multistream packet has 1 to 255 streams. With none, libopus takes the
+/// first stream for the last one and pads it without its self-delimiting length.
+fn check_streams(streams: usize) -> Result<(), Error> {
+    if streams == 0 || streams > 255 {
+        Err(Error::BadArg)
+    } else {
+        Ok(())
+    }
+}
+
+fn pad_lens(buf: &[u8], len: usize) -> Result<(opus_int32, opus_int32), Error> {
+    if len == 0 || len > buf.len() {
+        return Err(Error::BadArg);
+    }
+    Ok((len as opus_int32, params::packet_len(buf)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::Decoder;
+    use crate::encoder::Encoder;
+    use crate::multistream::{MultistreamDecoder, MultistreamEncoder};
+    use crate::testing::{encode_sine, sine};
+    use crate::types::Application;
+
+    /// TOC bytes of CELT fullband packets of each code, 20 ms frames.
+    const CODE0: u8 = 31 << 3;
+    const CODE1: u8 = CODE0 | 1;
+    const CODE2: u8 = CODE0 | 2;
+    const CODE3: u8 = CODE0 | 3;
+
+    fn bytes(head: &[u8], payload: usize) -> Vec<u8> {
+        let mut data = head.to_vec();
+        data.extend((0..payload).map(|i| i as u8));
+        data
+    }
+
+    /// Parses `data` both ways and checks they agree.
+    fn parse_both(data: &[u8]) -> Packet<'_> {
+        let packet = Packet::new(data).unwrap();
+        let parsed = parse(data).unwrap();
+        assert_eq!(packet.toc(), parsed.toc);
+        assert_eq!(packet.frames().collect::<Vec<_>>(), parsed.frames);
+        assert_eq!(packet.payload_offset(), parsed.payload_offset);
+        assert_eq!(packet.frame_count(), nb_frames(data).unwrap());
+        let samples = nb_samples(data, SampleRate::Hz48000).unwrap();
+        assert_eq!(packet.nb_samples(SampleRate::Hz48000), samples);
+        packet
+    }
+
+    fn violation(data: &[u8]) -> Violation {
+        assert_eq!(parse(data).unwrap_err(), Error::InvalidPacket);
+        Packet::new(data).unwrap_err()
+    }
+
+    #[test]
+    fn code0() {
+        let data = bytes(&[CODE0], 3);
+        let packet = parse_both(&data);
+        assert_eq!(packet.framing(), Framing::Single);
+        assert_eq!(packet.frame_sizes(), &[3]);
+
+        let packet = parse_both(&[CODE0]);
+        assert_eq!(packet.frame_sizes(), &[0]);
+        parse_both(&bytes(&[CODE0], MAX_FRAME_SIZE));
+    }
+
+    #[test]
+    fn code1() {
+        let data = bytes(&[CODE1], 4);
+        let packet = parse_both(&data);
+        assert_eq!(packet.framing(), Framing::TwoEqual);
+        assert_eq!(packet.frames().collect::<Vec<_>>(), [&[0, 1], &[2, 3]]);
+    }
+
+    #[test]
+    fn code2() {
+        let data = bytes(&[CODE2, 1], 3);
+        let packet = parse_both(&data);
+        assert_eq!(packet.framing(), Framing::TwoDifferent);
+        assert_eq!(packet.frame_sizes(), &[1, 2]);
+        assert_eq!(packet.payload_offset(), 2);
+
+        // Two-byte length, 252 + 4 * 12.
+        let data = bytes(&[CODE2, 252, 12], 310);
+        let packet = parse_both(&data);
+        assert_eq!(packet.frame_sizes(), &[300, 10]);
+        assert_eq!(packet.payload_offset(), 3);
+    }
+
+    #[test]
+    fn code3_cbr() {
+        let data = bytes(&[CODE3, 3], 6);
+        let packet = parse_both(&data);
+        assert_eq!(packet.framing(), Framing::Arbitrary { vbr: false });
+        assert_eq!(packet.frame_sizes(), &[2, 2, 2]);
+        assert_eq!(packet.payload_offset(), 2);
+
+        // 12 frames of 10 ms, the most there is room for.
+        let data = bytes(&[(30 << 3) | 3, 12], 24);
+        let packet = parse_both(&data);
+        assert_eq!(packet.frame_count(), 12);
+    }
+
+    #[test]
+    fn code3_vbr() {
+        let data = bytes(&[CODE3, 0x80 | 3, 1, 2], 6);
+        let packet = parse_both(&data);
+        assert_eq!(packet.framing(), Framing::Arbitrary { vbr: true });
+        assert_eq!(packet.frame_sizes(), &[1, 2, 3]);
+        assert_eq!(packet.payload_offset(), 4);
+    }
+
+    #[test]
+    fn code3_padding() {
+        // 255 continues the padding length with 254 bytes.
+        let data = bytes(&[CODE3, 0x40 | 2, 255, 10], 4 + 264);
+        let packet = parse_both(&data);
+        assert_eq!(packet.frame_sizes(), &[2, 2]);
+        assert_eq!(packet.padding(), 264);
+        assert_eq!(packet.payload_offset(), 4);
+
+        let data = bytes(&[CODE3, 0xc0 | 2, 255, 255, 0, 3], 5 + 508);
+        let packet = parse_both(&data);
+        assert_eq!(packet.frame_sizes(), &[3, 2]);
+        assert_eq!(packet.padding(), 508);
+        assert_eq!(packet.payload_offset(), 6);
+    }
+
+    #[test]
+    fn violations() {
+        assert_eq!(violation(&[]), Violation::R1);
+        assert_eq!(
+            violation(&bytes(&[CODE0], MAX_FRAME_SIZE + 1)),
+            Violation::R2
+        );
+        assert_eq!(violation(&bytes(&[CODE1], 3)), Violation::R3);
+        assert_eq!(violation(&[CODE2]), Violation::R4);
+        assert_eq!(violation(&bytes(&[CODE2, 5], 4)), Violation::R4);
+        assert_eq!(violation(&[CODE3, 0]), Violation::R5);
+        assert_eq!(violation(&bytes(&[CODE3, 7], 7)), Violation::R5);
+        assert_eq!(violation(&[CODE3]), Violation::R6);
+        assert_eq!(violation(&bytes(&[CODE3, 2], 3)), Violation::R6);
+        assert_eq!(violation(&bytes(&[CODE3, 0x40 | 2, 5], 4)), Violation::R6);
+        assert_eq!(violation(&[CODE3, 0x80 | 2]), Violation::R7);
+        assert_eq!(violation(&bytes(&[CODE3, 0x80 | 2, 10], 5)), Violation::R7);
+        assert_eq!(
+            violation(&bytes(&[CODE3, 0xc0 | 2, 255], 100)),
+            Violation::R7
+        );
+    }
+
+    /// A stereo 20 ms packet and what it decodes to.
+    fn encoded() -> (Vec<u8>, Vec<i16>) {
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        let packet = encode_sine(&mut encoder, 1).remove(0);
+        let pcm = decode(&packet);
+        (packet, pcm)
+    }
+
+    fn decode(packet: &[u8]) -> Vec<i16> {
+        let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        let mut pcm = vec![0i16; 960 * 2];
+        assert_eq!(decoder.decode(packet, &mut pcm).unwrap(), 960);
+        pcm
+    }
+
+    #[test]
+    fn pad_round_trip() {
+        let (original, pcm) = encoded();
+        let mut buf = vec![0; original.len() + 300];
+        buf[..original.len()].copy_from_slice(&original);
+        pad(&mut buf, original.len()).unwrap();
+
+        let packet = Packet::new(&buf).unwrap();
+        assert_eq!(packet.frame_count(), 1);
+        assert_eq!(packet.frames().next(), Some(&original[1..]));
+        assert_eq!(packet.padding() + packet.payload_offset(), 300 + 1);
+        assert_eq!(decode(&buf), pcm);
+
+        assert_eq!(unpad(&mut buf), Ok(original.len()));
+        assert_eq!(&buf[..original.len()], &original[..]);
+    }
+
+    #[test]
+    fn pad_rejects_bad_lengths() {
+        let (original, _) = encoded();
+        let mut buf = original.clone();
+        assert_eq!(pad(&mut buf, 0), Err(Error::BadArg));
+        assert_eq!(pad(&mut buf, original.len() + 1), Err(Error::BadArg));
+        // Code 1 needs an even payload for its two frames.
+        let mut invalid = bytes(&[CODE1], 3);
+        invalid.resize(20, 0);
+        assert_eq!(pad(&mut invalid, 4), Err(Error::InvalidPacket));
+        // Padding to the same length leaves the packet as it is.
+        assert_eq!(pad(&mut buf, original.len()), Ok(()));
+        assert_eq!(buf, original);
+        assert_eq!(unpad(&mut []), Err(Error::BadArg));
+    }
+
+    #[test]
+    fn multistream_pad_round_trip() {
+        let mapping = [0, 1];
+        let mut encoder =
+            MultistreamEncoder::new(SampleRate::Hz48000, 2, 0, &mapping, Application::Audio)
+                .unwrap();
+        let mut original = [0; 1500];
+        let len = encoder.encode(&sine(0, 2), &mut original).unwrap();
+        let original = &original[..len];
+        let decode = |packet: &[u8]| {
+            let mut decoder = MultistreamDecoder::new(SampleRate::Hz48000, 2, 0, &mapping).unwrap();
+            let mut pcm = vec![0i16; 960 * 2];
+            assert_eq!(decoder.decode(packet, &mut pcm).unwrap(), 960);
+            pcm
+        };
+
+        let mut buf = vec![0; len + 300];
+        buf[..len].copy_from_slice(original);
+        multistream_pad(&mut buf, len, 2).unwrap();
+        assert_eq!(decode(&buf), decode(original));
+
+        assert_eq!(multistream_unpad(&mut buf, 2), Ok(len));
+        assert_eq!(&buf[..len], original);
+        assert_eq!(multistream_pad(&mut buf, len, 0), Err(Error::BadArg));
+        assert_eq!(multistream_unpad(&mut buf[..len], 0), Err(Error::BadArg));
+    }
+}
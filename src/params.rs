@@ -0,0 +1,67 @@
+use std::os::raw::c_int;
+
+use crate::error::Error;
+use crate::sys::*;
+use crate::types::{FrameDuration, SampleRate};
+
+/// Checks that `frame_size` samples per channel is a valid Opus frame at `sample_rate`.
+pub(crate) fn check_frame_size(sample_rate: SampleRate, frame_size: usize) -> Result<c_int, Error> {
+    FrameDuration::from_samples(sample_rate, frame_size)
+        .map(|_| frame_size as c_int)
+        .ok_or(Error::BadArg)
+}
+
+/// Checks that `samples` per channel is a non-zero multiple of 2.5 ms at `sample_rate`.
+pub(crate) fn check_duration(sample_rate: SampleRate, samples: usize) -> Result<usize, Error> {
+    let unit = FrameDuration::Ms2_5.samples(sample_rate);
+    if samples != 0 && samples.is_multiple_of(unit) && samples <= c_int::MAX as usize {
+        Ok(samples)
+    } else {
+        Err(Error::BadArg)
+    }
+}
+
+/// Splits an interleaved buffer into samples per channel, rejecting partial frames.
+pub(crate) fn samples_per_channel(len: usize, channels: usize) -> Result<usize, Error> {
+    if len.is_multiple_of(channels) {
+        Ok(len / channels)
+    } else {
+        Err(Error::BadArg)
+    }
+}
+
+/// Samples per channel of planar buffers, rejecting a plane count other than
+/// `channels` and planes of different lengths.
+pub(crate) fn planar_len<I>(planes: I, channels: usize) -> Result<usize, Error>
+where
+    I: ExactSizeIterator<Item = usize>,
+{
+    if planes.len() != channels {
+        return Err(Error::BadArg);
+    }
+    let mut len = None;
+    for plane in planes {
+        if *len.get_or_insert(plane) != plane {
+            return Err(Error::BadArg);
+        }
+    }
+    Ok(len.unwrap_or(0))
+}
+
+/// Output buffer length as `opus_int32`, libopus can't use more anyway.
+pub(crate) fn buffer_len(len: usize) -> Result<opus_int32, Error> {
+    if len == 0 {
+        Err(Error::BufferTooSmall)
+    } else {
+        Ok(len.min(opus_int32::MAX as usize) as opus_int32)
+    }
+}
+
+/// Input packet length as `opus_int32`.
+pub(crate) fn packet_len(packet: &[u8]) -> Result<opus_int32, Error> {
+    if packet.len() > opus_int32::MAX as usize {
+        Err(Error::BadArg)
+    } else {
+        Ok(packet.len() as opus_int32)
+    }
+}
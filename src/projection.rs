@@ -0,0 +1,390 @@
+use std::os::raw::c_int;
+
+use crate::codec::{Call, DecoderCore, EncoderCore};
+use crate::config::EncoderConfig;
+use crate::ctl::projection_encoder::{
+    get_demixing_matrix, get_demixing_matrix_gain, get_demixing_matrix_size,
+};
+use crate::error::{check, Error};
+use crate::sample::Sample;
+use crate::state::OwnedState;
+use crate::sys::*;
+use crate::types::{Application, SampleRate};
+
+/// Ambisonics encoder of mapping family 3: the channels are mixed into
+/// streams by a projection matrix, undone by the decoder with the demixing
+/// matrix.
+///
+/// Input is interleaved ACN/SN3D PCM of order 1 to 3, optionally followed by
+/// a non-diegetic stereo pair, in any [`Sample`] format.
+/// [`streams`](Self::streams), [`coupled_streams`](Self::coupled_streams) and
+/// [`demixing_matrix`](Self::demixing_matrix) go into the container header
+/// and to [`ProjectionDecoder::new`].
+#[derive(Debug)]
+pub struct ProjectionEncoder {
+    core: EncoderCore<OpusProjectionEncoder>,
+    _memory: OwnedState,
+    order: u8,
+    non_diegetic: bool,
+    streams: usize,
+    coupled_streams: usize,
+    demixing_matrix: Vec<u8>,
+    demixing_matrix_gain: i32,
+}
+
+impl ProjectionEncoder {
+    /// Encoder for ambisonics of `order` 1 to 3, with a non-diegetic stereo
+    /// pair after the ambisonic channels if `non_diegetic` is set.
+    pub fn ambisonics(
+        sample_rate: SampleRate,
+        order: u8,
+        non_diegetic: bool,
+        application: Application,
+    ) -> Result<Self, Error> {
+        let channels = ambisonic_channels(order, non_diegetic)?;
+        let channels_raw = channels as c_int;
+        let size = unsafe { opus_projection_ambisonics_encoder_get_size(channels_raw, 3) };
+        if size <= 0 {
+            return Err(Error::BadArg);
+        }
+        let memory = OwnedState::new(size as usize);
+        let state = memory.as_state::<OpusProjectionEncoder>();
+        let mut streams = 0;
+        let mut coupled_streams = 0;
+        check(unsafe {
+            opus_projection_ambisonics_encoder_init(
+                state.as_ptr(),
+                sample_rate.raw(),
+                channels_raw,
+                3,
+                &mut streams,
+                &mut coupled_streams,
+                application.raw(),
+            )
+        })?;
+
+        let mut gain = 0;
+        let mut matrix_size = 0;
+        unsafe {
+            check(get_demixing_matrix_gain(state.as_ptr(), &mut gain))?;
+            check(get_demixing_matrix_size(state.as_ptr(), &mut matrix_size))?;
+        }
+        let mut demixing_matrix = vec![0; matrix_size as usize];
+        check(unsafe { get_demixing_matrix(state.as_ptr(), &mut demixing_matrix) })?;
+
+        Ok(ProjectionEncoder {
+            core: EncoderCore::new(state, sample_rate, channels),
+            _memory: memory,
+            order,
+            non_diegetic,
+            streams: streams as usize,
+            coupled_streams: coupled_streams as usize,
+            demixing_matrix,
+            demixing_matrix_gain: gain,
+        })
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.core.sample_rate()
+    }
+
+    /// Ambisonic order, 1 to 3.
+    pub fn order(&self) -> u8 {
+        self.order
+    }
+
+    /// Whether a non-diegetic stereo pair follows the ambisonic channels.
+    pub fn non_diegetic(&self) -> bool {
+        self.non_diegetic
+    }
+
+    /// Input channels, `(order + 1)^2`, plus 2 with the non-diegetic pair.
+    pub fn channels(&self) -> usize {
+        self.core.channels()
+    }
+
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    pub fn coupled_streams(&self) -> usize {
+        self.coupled_streams
+    }
+
+    /// Demixing matrix as stored in the container header: 16-bit
+    /// little-endian coefficients, column-major, one row per output channel
+    /// and one column per decoded stream channel.
+    pub fn demixing_matrix(&self) -> &[u8] {
+        &self.demixing_matrix
+    }
+
+    /// Gain of the demixing matrix in dB, Q7.8 fixed point.
+    pub fn demixing_matrix_gain(&self) -> i32 {
+        self.demixing_matrix_gain
+    }
+
+    /// Encodes one frame of PCM into `output`, returns the packet length.
+    pub fn encode<T: Sample>(&mut self, pcm: &[T], output: &mut [u8]) -> Result<usize, Error> {
+        self.core.encode(pcm, output)
+    }
+
+    /// Float version of [`encode`](Self::encode), input in the +/-1.0 range.
+    pub fn encode_float(&mut self, pcm: &[f32], output: &mut [u8]) -> Result<usize, Error> {
+        self.encode(pcm, output)
+    }
+
+    /// Applies every setting of `config` to all streams. Settings after the
+    /// first one libopus rejects are not applied.
+    pub fn apply_config(&mut self, config: &EncoderConfig) -> Result<(), Error> {
+        self.core.apply_config(config)
+    }
+
+    /// Effective settings, read back from libopus, those of the first stream
+    /// except for the bitrate, the total of all streams as of the last frame.
+    pub fn config(&mut self) -> Result<EncoderConfig, Error> {
+        self.core.config()
+    }
+
+    /// Raw state for [`ctl::projection_encoder`](crate::ctl::projection_encoder)
+    /// and other raw calls. It stays owned by the encoder.
+    pub fn as_mut_ptr(&mut self) -> *mut OpusProjectionEncoder {
+        self.core.as_ptr()
+    }
+}
+
+/// Decoder of mapping family 3, the counterpart of [`ProjectionEncoder`].
+///
+/// Output is interleaved PCM in any [`Sample`] format, demixed back to the
+/// ambisonic channels. Every call returns the number of samples per channel
+/// written to the start of `pcm`.
+#[derive(Debug)]
+pub struct ProjectionDecoder {
+    core: DecoderCore<OpusProjectionDecoder>,
+    _memory: OwnedState,
+    channels: usize,
+    streams: usize,
+    coupled_streams: usize,
+}
+
+impl ProjectionDecoder {
+    /// Decoder for `channels` output channels from the layout and demixing
+    /// matrix written by the encoder. The matrix must hold
+    /// `(streams + coupled_streams) * channels` coefficients.
+    pub fn new(
+        sample_rate: SampleRate,
+        channels: usize,
+        streams: usize,
+        coupled_streams: usize,
+        demixing_matrix: &[u8],
+    ) -> Result<Self, Error> {
+        if channels == 0
+            || channels > 255
+            || streams == 0
+            || coupled_streams > streams
+            || streams + coupled_streams > 255
+            || demixing_matrix.len() != (streams + coupled_streams) * channels * 2
+        {
+            return Err(Error::BadArg);
+        }
+        let (channels_raw, streams_raw, coupled_raw) = (
+            channels as c_int,
+            streams as c_int,
+            coupled_streams as c_int,
+        );
+        let size =
+            unsafe { opus_projection_decoder_get_size(channels_raw, streams_raw, coupled_raw) };
+        if size <= 0 {
+            return Err(Error::BadArg);
+        }
+        let memory = OwnedState::new(size as usize);
+        let state = memory.as_state::<OpusProjectionDecoder>();
+        check(unsafe {
+            // The matrix is only read, the pointer is mutable in the C API.
+            opus_projection_decoder_init(
+                state.as_ptr(),
+                sample_rate.raw(),
+                channels_raw,
+                streams_raw,
+                coupled_raw,
+                demixing_matrix.as_ptr() as *mut u8,
+                demixing_matrix.len() as opus_int32,
+            )
+        })?;
+
+        Ok(ProjectionDecoder {
+            core: DecoderCore::new(state, sample_rate, channels),
+            _memory: memory,
+            channels,
+            streams,
+            coupled_streams,
+        })
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.core.sample_rate()
+    }
+
+    /// Output channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    pub fn coupled_streams(&self) -> usize {
+        self.coupled_streams
+    }
+
+    /// Adds TPDF dither when decoding to integer formats. `i16` output is then
+    /// decoded through float too. Off by default.
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.core.set_dither(enabled);
+    }
+
+    /// Decodes `packet`, `pcm` must hold its duration for every channel or
+    /// [`Error::BufferTooSmall`] is returned.
+    pub fn decode<T: Sample>(&mut self, packet: &[u8], pcm: &mut [T]) -> Result<usize, Error> {
+        self.core.call(packet, pcm, Call::Decode)
+    }
+
+    /// Float version of [`decode`](Self::decode).
+    pub fn decode_float(&mut self, packet: &[u8], pcm: &mut [f32]) -> Result<usize, Error> {
+        self.decode(packet, pcm)
+    }
+
+    /// Packet loss concealment: synthesizes a lost frame filling all of `pcm`.
+    /// Its duration must be a multiple of 2.5 ms.
+    pub fn conceal<T: Sample>(&mut self, pcm: &mut [T]) -> Result<usize, Error> {
+        self.core.call(&[], pcm, Call::Lost { fec: false })
+    }
+
+    /// Recovers the frame lost right before `next_packet` from the in-band
+    /// FEC data of its streams, filling all of `pcm`. See
+    /// [`Decoder::decode_fec`](crate::Decoder::decode_fec).
+    pub fn decode_fec<T: Sample>(
+        &mut self,
+        next_packet: &[u8],
+        pcm: &mut [T],
+    ) -> Result<usize, Error> {
+        self.core.call(next_packet, pcm, Call::Lost { fec: true })
+    }
+
+    /// Raw state for [`ctl::projection_decoder`](crate::ctl::projection_decoder)
+    /// and other raw calls. It stays owned by the decoder.
+    pub fn as_mut_ptr(&mut self) -> *mut OpusProjectionDecoder {
+        self.core.as_ptr()
+    }
+}
+
+/// Channels of ambisonics of `order`, [`Error::BadArg`] outside the orders
+/// libopus has matrices for.
+fn ambisonic_channels(order: u8, non_diegetic: bool) -> Result<usize, Error> {
+    if !(1..=3).contains(&order) {
+        return Err(Error::BadArg);
+    }
+    Ok((usize::from(order) + 1).pow(2) + if non_diegetic { 2 } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{sine, FRAME};
+
+    fn ambisonics(order: u8, non_diegetic: bool) -> Result<ProjectionEncoder, Error> {
+        ProjectionEncoder::ambisonics(SampleRate::Hz48000, order, non_diegetic, Application::Audio)
+    }
+
+    #[test]
+    fn layouts() {
+        let expected = [
+            (1, false, 4, 2, 2),
+            (1, true, 6, 3, 3),
+            (2, false, 9, 5, 4),
+            (2, true, 11, 6, 5),
+            (3, false, 16, 8, 8),
+            (3, true, 18, 9, 9),
+        ];
+        for &(order, non_diegetic, channels, streams, coupled_streams) in &expected {
+            let encoder = ambisonics(order, non_diegetic).unwrap();
+            assert_eq!(encoder.order(), order);
+            assert_eq!(encoder.non_diegetic(), non_diegetic);
+            assert_eq!(encoder.channels(), channels);
+            assert_eq!(encoder.streams(), streams);
+            assert_eq!(encoder.coupled_streams(), coupled_streams);
+            let coefficients = (streams + coupled_streams) * channels;
+            assert_eq!(encoder.demixing_matrix().len(), coefficients * 2);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_channel_counts() {
+        for &order in &[0, 4, 255] {
+            assert_eq!(ambisonics(order, false).err(), Some(Error::BadArg));
+            assert_eq!(ambisonics(order, true).err(), Some(Error::BadArg));
+        }
+
+        let encoder = ambisonics(1, false).unwrap();
+        let matrix = encoder.demixing_matrix();
+        let decoder = |channels, streams, coupled_streams, matrix: &[u8]| {
+            ProjectionDecoder::new(
+                SampleRate::Hz48000,
+                channels,
+                streams,
+                coupled_streams,
+                matrix,
+            )
+            .err()
+        };
+        assert_eq!(decoder(4, 2, 2, matrix), None);
+        assert_eq!(decoder(0, 2, 2, &[]), Some(Error::BadArg));
+        assert_eq!(
+            decoder(256, 2, 2, &vec![0; 256 * 4 * 2]),
+            Some(Error::BadArg)
+        );
+        assert_eq!(decoder(5, 2, 2, matrix), Some(Error::BadArg));
+        assert_eq!(decoder(4, 0, 0, &[]), Some(Error::BadArg));
+        assert_eq!(decoder(4, 2, 3, matrix), Some(Error::BadArg));
+        assert_eq!(
+            decoder(4, 2, 2, &matrix[..matrix.len() - 2]),
+            Some(Error::BadArg)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for &(order, non_diegetic) in &[(1, false), (2, true), (3, false)] {
+            let mut encoder = ambisonics(order, non_diegetic).unwrap();
+            let channels = encoder.channels();
+            let mut decoder = ProjectionDecoder::new(
+                SampleRate::Hz48000,
+                channels,
+                encoder.streams(),
+                encoder.coupled_streams(),
+                encoder.demixing_matrix(),
+            )
+            .unwrap();
+
+            // The sine on the omnidirectional channel W only.
+            let mut input = vec![0i16; FRAME * channels];
+            let mut packet = [0; 8000];
+            let mut pcm = vec![0i16; FRAME * channels];
+            for index in 0..5 {
+                for (frame, &sample) in input.chunks_mut(channels).zip(&sine(index, 1)) {
+                    frame[0] = sample;
+                }
+                let len = encoder.encode(&input, &mut packet).unwrap();
+                assert_eq!(decoder.decode(&packet[..len], &mut pcm).unwrap(), FRAME);
+            }
+            let peak = |channel: usize| {
+                let samples = pcm.iter().skip(channel).step_by(channels);
+                samples.map(|sample| sample.abs()).max().unwrap()
+            };
+            // The gain of the demixing matrix is left to the player.
+            let gain = 10f32.powf(encoder.demixing_matrix_gain() as f32 / 256.0 / 20.0);
+            assert!(f32::from(peak(0)) * gain > 7000.0, "order {}", order);
+            assert!((1..channels).all(|channel| peak(channel) < peak(0) / 4));
+        }
+    }
+}
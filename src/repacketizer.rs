@@ -0,0 +1,240 @@
+use std::marker::PhantomData;
+use std::os::raw::c_int;
+use std::ptr::NonNull;
+
+use crate::error::{check, Error};
+use crate::packet::MAX_FRAME_SIZE;
+use crate::params;
+use crate::state::OwnedState;
+use crate::sys::*;
+
+/// Merges packets into one and splits packets into frames, without decoding.
+///
+/// Added packets are referenced, not copied, so they are borrowed for `'a`.
+/// [`reset`](Self::reset) releases them.
+#[derive(Debug)]
+pub struct Repacketizer<'a> {
+    state: NonNull<OpusRepacketizer>,
+    _memory: OwnedState,
+    _packets: PhantomData<&'a [u8]>,
+}
+
+// The state only points to the borrowed packets, which are Sync.
+unsafe impl Send for Repacketizer<'_> {}
+
+impl<'a> Repacketizer<'a> {
+    pub fn new() -> Self {
+        let memory = OwnedState::new(unsafe { opus_repacketizer_get_size() } as usize);
+        let state = memory.as_state::<OpusRepacketizer>();
+        unsafe { opus_repacketizer_init(state.as_ptr()) };
+        Repacketizer {
+            state,
+            _memory: memory,
+            _packets: PhantomData,
+        }
+    }
+
+    /// Drops every added packet, the repacketizer can then take packets with
+    /// another lifetime.
+    pub fn reset<'b>(self) -> Repacketizer<'b> {
+        unsafe { opus_repacketizer_init(self.state.as_ptr()) };
+        Repacketizer {
+            state: self.state,
+            _memory: self._memory,
+            _packets: PhantomData,
+        }
+    }
+
+    /// Adds the frames of `packet`. Fails with [`Error::InvalidPacket`] if it
+    /// is malformed, its TOC configuration (mode, bandwidth, frame size and
+    /// channels) differs from the packets already added, or the total would
+    /// exceed 120 ms. The repacketizer is unchanged then.
+    pub fn cat(&mut self, packet: &'a [u8]) -> Result<(), Error> {
+        let len = params::packet_len(packet)?;
+        check(unsafe { opus_repacketizer_cat(self.state.as_ptr(), packet.as_ptr(), len) })?;
+        Ok(())
+    }
+
+    /// Frames added so far.
+    pub fn nb_frames(&self) -> usize {
+        unsafe { opus_repacketizer_get_nb_frames(self.state.as_ptr()) as usize }
+    }
+
+    /// Writes frames `begin..end` as one packet into `output`, returns its length.
+    pub fn out_range(
+        &mut self,
+        begin: usize,
+        end: usize,
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        if begin >= end || end > self.nb_frames() {
+            return Err(Error::BadArg);
+        }
+        let len = unsafe {
+            opus_repacketizer_out_range(
+                self.state.as_ptr(),
+                begin as c_int,
+                end as c_int,
+                output.as_mut_ptr(),
+                params::buffer_len(output.len())?,
+            )
+        };
+        Ok(check(len)? as usize)
+    }
+
+    /// Writes all frames as one packet into `output`, returns its length.
+    pub fn out(&mut self, output: &mut [u8]) -> Result<usize, Error> {
+        self.out_range(0, self.nb_frames(), output)
+    }
+
+    /// Merges `packets`, e.g. three 20 ms packets into a 60 ms one, into
+    /// `output`, returns its length.
+    pub fn merge(packets: &[&[u8]], output: &mut [u8]) -> Result<usize, Error> {
+        let mut repacketizer = Repacketizer::new();
+        for packet in packets {
+            repacketizer.cat(packet)?;
+        }
+        repacketizer.out(output)
+    }
+
+    /// Splits `packet` into single-frame packets.
+    pub fn split(packet: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut repacketizer = Repacketizer::new();
+        repacketizer.cat(packet)?;
+        let mut buf = [0; 1 + MAX_FRAME_SIZE];
+        (0..repacketizer.nb_frames())
+            .map(|frame| {
+                let len = repacketizer.out_range(frame, frame + 1, &mut buf)?;
+                Ok(buf[..len].to_vec())
+            })
+            .collect()
+    }
+}
+
+impl Default for Repacketizer<'_> {
+    fn default() -> Self {
+        Repacketizer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::Decoder;
+    use crate::encoder::Encoder;
+    use crate::packet::Packet;
+    use crate::testing::encode_sine;
+    use crate::types::{Application, Channels, FrameDuration, SampleRate};
+
+    fn encode(channels: Channels, count: usize) -> Vec<Vec<u8>> {
+        let mut encoder = Encoder::new(SampleRate::Hz48000, channels, Application::Audio).unwrap();
+        encode_sine(&mut encoder, count)
+    }
+
+    /// Decodes `packets` one after the other with a fresh stereo decoder.
+    fn decode(packets: &[&[u8]]) -> Vec<i16> {
+        let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+        let mut pcm = vec![0i16; 5760 * 2];
+        let mut decoded = Vec::new();
+        for packet in packets {
+            let samples = decoder.decode(packet, &mut pcm).unwrap();
+            decoded.extend_from_slice(&pcm[..samples * 2]);
+        }
+        decoded
+    }
+
+    #[test]
+    fn merges_into_one_packet() {
+        let packets = encode(Channels::Stereo, 3);
+        let packets: Vec<&[u8]> = packets.iter().map(Vec::as_slice).collect();
+        let mut output = [0; 1500];
+        let len = Repacketizer::merge(&packets, &mut output).unwrap();
+
+        let merged = Packet::new(&output[..len]).unwrap();
+        assert_eq!(merged.frame_count(), 3);
+        assert_eq!(merged.frame_duration(), FrameDuration::Ms20);
+        assert_eq!(merged.nb_samples(SampleRate::Hz48000), 2880);
+        assert_eq!(
+            merged.toc().config(),
+            Packet::new(packets[0]).unwrap().toc().config()
+        );
+        assert_eq!(decode(&[&output[..len]]), decode(&packets));
+    }
+
+    #[test]
+    fn splits_into_single_frames() {
+        let packets = encode(Channels::Stereo, 3);
+        let packets: Vec<&[u8]> = packets.iter().map(Vec::as_slice).collect();
+        let mut output = [0; 1500];
+        let len = Repacketizer::merge(&packets, &mut output).unwrap();
+
+        let frames = Repacketizer::split(&output[..len]).unwrap();
+        assert_eq!(frames.len(), 3);
+        for (frame, packet) in frames.iter().zip(&packets) {
+            assert_eq!(Packet::new(frame).unwrap().frame_count(), 1);
+            assert_eq!(frame, packet);
+        }
+        let frames: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+        assert_eq!(decode(&frames), decode(&packets));
+    }
+
+    #[test]
+    fn cat_rejects_other_configuration() {
+        let stereo = encode(Channels::Stereo, 1);
+        let mono = encode(Channels::Mono, 1);
+        let mut repacketizer = Repacketizer::new();
+        repacketizer.cat(&stereo[0]).unwrap();
+        assert_eq!(repacketizer.cat(&mono[0]), Err(Error::InvalidPacket));
+        assert_eq!(repacketizer.cat(&[]), Err(Error::InvalidPacket));
+        assert_eq!(repacketizer.nb_frames(), 1);
+
+        let mut output = [0; 1500];
+        let len = repacketizer.out(&mut output).unwrap();
+        assert_eq!(&output[..len], &stereo[0][..]);
+    }
+
+    #[test]
+    fn cat_rejects_more_than_120_ms() {
+        let packets = encode(Channels::Stereo, 7);
+        let mut repacketizer = Repacketizer::new();
+        for packet in &packets[..6] {
+            repacketizer.cat(packet).unwrap();
+        }
+        assert_eq!(repacketizer.cat(&packets[6]), Err(Error::InvalidPacket));
+        assert_eq!(repacketizer.nb_frames(), 6);
+    }
+
+    #[test]
+    fn out_range_rejects_bad_ranges() {
+        let packets = encode(Channels::Stereo, 2);
+        let mut output = [0; 1500];
+        let mut repacketizer = Repacketizer::new();
+        assert_eq!(repacketizer.out(&mut output), Err(Error::BadArg));
+
+        repacketizer.cat(&packets[0]).unwrap();
+        repacketizer.cat(&packets[1]).unwrap();
+        assert_eq!(
+            repacketizer.out_range(1, 1, &mut output),
+            Err(Error::BadArg)
+        );
+        assert_eq!(
+            repacketizer.out_range(1, 0, &mut output),
+            Err(Error::BadArg)
+        );
+        assert_eq!(
+            repacketizer.out_range(0, 3, &mut output),
+            Err(Error::BadArg)
+        );
+        assert_eq!(
+            repacketizer.out_range(0, 1, &mut []),
+            Err(Error::BufferTooSmall)
+        );
+        assert_eq!(
+            repacketizer.out_range(0, 1, &mut output[..10]),
+            Err(Error::BufferTooSmall)
+        );
+
+        let len = repacketizer.out_range(1, 2, &mut output).unwrap();
+        assert_eq!(&output[..len], &packets[1][..]);
+    }
+}
@@ -0,0 +1,279 @@
+use std::slice;
+
+/// PCM sample format accepted by the safe encoders and decoders.
+///
+/// `i16` and `f32` go straight to the native `opus_encode`/`opus_decode` and
+/// `*_float` entry points. Other formats are converted through float, scaled
+/// so that full scale maps to +/-1.0 and clipped on the way back.
+///
+/// The trait is sealed, it is implemented for `i16`, `f32`, `i32` and [`I24`].
+pub trait Sample: Copy + Default + sealed::Sealed {
+    /// Converts to float, full scale is +/-1.0.
+    fn to_f32(self) -> f32;
+
+    /// Converts from float with rounding, clipping values outside +/-1.0.
+    fn from_f32(value: f32) -> Self;
+
+    #[doc(hidden)]
+    const FORMAT: Format;
+}
+
+/// How a sample type reaches libopus.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Native 16-bit entry points.
+    I16,
+    /// Native float entry points.
+    F32,
+    /// Integer converted through float, with its full scale.
+    Int(f32),
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for i16 {}
+    impl Sealed for f32 {}
+    impl Sealed for i32 {}
+    impl Sealed for super::I24 {}
+}
+
+impl Sample for i16 {
+    const FORMAT: Format = Format::I16;
+
+    fn to_f32(self) -> f32 {
+        f32::from(self) / 32768.0
+    }
+
+    fn from_f32(value: f32) -> Self {
+        // Float to int casts saturate.
+        (value * 32768.0).round() as i16
+    }
+}
+
+impl Sample for f32 {
+    const FORMAT: Format = Format::F32;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
+
+impl Sample for i32 {
+    const FORMAT: Format = Format::Int(2_147_483_648.0);
+
+    fn to_f32(self) -> f32 {
+        self as f32 / 2_147_483_648.0
+    }
+
+    fn from_f32(value: f32) -> Self {
+        (f64::from(value) * 2_147_483_648.0).round() as i32
+    }
+}
+
+/// Packed 24-bit little-endian sample, 3 bytes in memory.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct I24([u8; 3]);
+
+impl I24 {
+    pub const MIN: i32 = -(1 << 23);
+    pub const MAX: i32 = (1 << 23) - 1;
+
+    /// Saturates `value` to the 24-bit range.
+    pub fn new(value: i32) -> Self {
+        let bytes = value.clamp(Self::MIN, Self::MAX).to_le_bytes();
+        I24([bytes[0], bytes[1], bytes[2]])
+    }
+
+    pub fn get(self) -> i32 {
+        let [b0, b1, b2] = self.0;
+        // Place the sign bit at the top, then shift back with sign extension.
+        i32::from_le_bytes([0, b0, b1, b2]) >> 8
+    }
+
+    pub fn from_le_bytes(bytes: [u8; 3]) -> Self {
+        I24(bytes)
+    }
+
+    pub fn to_le_bytes(self) -> [u8; 3] {
+        self.0
+    }
+}
+
+impl From<I24> for i32 {
+    fn from(value: I24) -> i32 {
+        value.get()
+    }
+}
+
+impl Sample for I24 {
+    const FORMAT: Format = Format::Int(8_388_608.0);
+
+    fn to_f32(self) -> f32 {
+        self.get() as f32 / 8_388_608.0
+    }
+
+    fn from_f32(value: f32) -> Self {
+        I24::new((value * 8_388_608.0).round() as i32)
+    }
+}
+
+/// Converts decoded float to `T`, with dither at the LSB of integer formats
+/// if `dither` is set.
+pub(crate) fn from_f32<T: Sample>(value: f32, dither: Option<&mut Dither>) -> T {
+    let lsb = match T::FORMAT {
+        Format::I16 => 1.0 / 32768.0,
+        Format::Int(scale) => 1.0 / scale,
+        Format::F32 => return T::from_f32(value),
+    };
+    match dither {
+        Some(dither) => T::from_f32(value + dither.next() * lsb),
+        None => T::from_f32(value),
+    }
+}
+
+/// `pcm` as `i16`, if that is its type.
+pub(crate) fn as_i16<T: Sample>(pcm: &[T]) -> Option<&[i16]> {
+    match T::FORMAT {
+        // Sound, the trait is sealed and only i16 uses this format.
+        Format::I16 => {
+            Some(unsafe { slice::from_raw_parts(pcm.as_ptr() as *const i16, pcm.len()) })
+        }
+        _ => None,
+    }
+}
+
+/// `pcm` as `i16`, if that is its type.
+pub(crate) fn as_i16_mut<T: Sample>(pcm: &mut [T]) -> Option<&mut [i16]> {
+    match T::FORMAT {
+        Format::I16 => {
+            Some(unsafe { slice::from_raw_parts_mut(pcm.as_mut_ptr() as *mut i16, pcm.len()) })
+        }
+        _ => None,
+    }
+}
+
+/// Interleaves `frame_size` samples of each plane into `out`, converting them.
+pub(crate) fn interleave<'a, T, U, I, F>(
+    planes: I,
+    channels: usize,
+    frame_size: usize,
+    out: &mut Vec<U>,
+    mut convert: F,
+) where
+    T: Copy + 'a,
+    U: Copy + Default,
+    I: Iterator<Item = &'a [T]>,
+    F: FnMut(T) -> U,
+{
+    out.clear();
+    out.resize(frame_size * channels, U::default());
+    for (channel, plane) in planes.enumerate() {
+        for (frame, &sample) in out.chunks_exact_mut(channels).zip(plane) {
+            frame[channel] = convert(sample);
+        }
+    }
+}
+
+/// Splits the interleaved `pcm` into `planes`, as far as both reach.
+pub(crate) fn deinterleave<'a, T, U, I, F>(pcm: &[T], channels: usize, planes: I, mut convert: F)
+where
+    T: Copy,
+    U: 'a,
+    I: Iterator<Item = &'a mut [U]>,
+    F: FnMut(T) -> U,
+{
+    for (channel, plane) in planes.enumerate() {
+        for (sample, frame) in plane.iter_mut().zip(pcm.chunks_exact(channels)) {
+            *sample = convert(frame[channel]);
+        }
+    }
+}
+
+/// Interleaving buffers reused across calls.
+#[derive(Debug, Default)]
+pub(crate) struct Scratch {
+    pub(crate) i16: Vec<i16>,
+    pub(crate) f32: Vec<f32>,
+}
+
+/// TPDF dither for float to integer conversion, one LSB peak.
+#[derive(Debug, Clone)]
+pub(crate) struct Dither(u32);
+
+impl Dither {
+    pub(crate) fn new() -> Self {
+        Dither(0x2545_f491)
+    }
+
+    /// Noise in +/-1.0, to be scaled to the LSB of the target format.
+    pub(crate) fn next(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+
+    // xorshift32, plenty for dither.
+    fn uniform(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        (x >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn i24_get_sign_extends() {
+        assert_eq!(I24::from_le_bytes([0xff, 0xff, 0xff]).get(), -1);
+        assert_eq!(I24::from_le_bytes([0x00, 0x00, 0x80]).get(), I24::MIN);
+        assert_eq!(I24::from_le_bytes([0xff, 0xff, 0x7f]).get(), I24::MAX);
+        assert_eq!(I24::from_le_bytes([0x56, 0x34, 0x12]).get(), 0x12_3456);
+        assert_eq!(I24::new(-0x12_3456).get(), -0x12_3456);
+    }
+
+    #[test]
+    fn i24_new_saturates() {
+        assert_eq!(I24::new(i32::MAX).get(), I24::MAX);
+        assert_eq!(I24::new(i32::MIN).get(), I24::MIN);
+        assert_eq!(I24::new(I24::MAX + 1).to_le_bytes(), [0xff, 0xff, 0x7f]);
+        assert_eq!(I24::new(I24::MIN - 1).to_le_bytes(), [0x00, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn from_f32_saturates_at_full_scale() {
+        assert_eq!(i16::from_f32(1.0), 32767);
+        assert_eq!(i16::from_f32(-1.0), -32768);
+        assert_eq!(i16::from_f32(2.0), 32767);
+        assert_eq!(i16::from_f32(-2.0), -32768);
+        assert_eq!(i32::from_f32(1.0), i32::MAX);
+        assert_eq!(i32::from_f32(-1.0), i32::MIN);
+        assert_eq!(I24::from_f32(1.0).get(), I24::MAX);
+        assert_eq!(I24::from_f32(-1.0).get(), I24::MIN);
+        assert_eq!(I24::from_f32(-2.0).get(), I24::MIN);
+    }
+
+    #[test]
+    fn dither_stays_within_one_lsb() {
+        let mut dither = Dither::new();
+        let noise: Vec<f32> = (0..100_000).map(|_| dither.next()).collect();
+        assert!(noise.iter().all(|value| value.abs() < 1.0));
+        let mean = noise.iter().sum::<f32>() / noise.len() as f32;
+        assert!(mean.abs() < 0.01, "mean {}", mean);
+
+        let mut dither = Dither::new();
+        for _ in 0..1000 {
+            let value: i16 = from_f32(0.25, Some(&mut dither));
+            assert!((8191..=8193).contains(&value), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+
+use crate::error::Error;
+
+/// Alignment of codec states, what malloc guarantees on common platforms.
+pub const STATE_ALIGN: usize = 16;
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+struct Block([u8; STATE_ALIGN]);
+
+/// State memory allocated as a boxed slice, without going through libopus'
+/// malloc. Freed on drop.
+///
+/// Only the `new` constructors of the codecs create it, sized for their state
+/// and [`STATE_ALIGN`]-aligned, which a `Box<[u8]>` from the caller can't
+/// promise. Memory the caller manages is used through [`BorrowedState`].
+#[derive(Debug)]
+pub struct OwnedState {
+    ptr: NonNull<Block>,
+    blocks: usize,
+}
+
+// Plain memory, only reachable through the codec owning it.
+unsafe impl Send for OwnedState {}
+
+impl OwnedState {
+    /// Zeroed memory for `size` bytes of state.
+    pub(crate) fn new(size: usize) -> Self {
+        let blocks = size.div_ceil(STATE_ALIGN);
+        let memory = vec![Block([0; STATE_ALIGN]); blocks].into_boxed_slice();
+        // Kept as a raw pointer, the codec writes through its own copy of it.
+        let ptr = Box::into_raw(memory) as *mut Block;
+        OwnedState {
+            ptr: NonNull::new(ptr).unwrap_or(NonNull::dangling()),
+            blocks,
+        }
+    }
+
+    /// Copy of the `size` bytes of state at `src`.
+    pub(crate) unsafe fn copy_of(src: *const u8, size: usize) -> Self {
+        let memory = OwnedState::new(size);
+        ptr::copy_nonoverlapping(src, memory.as_ptr(), size);
+        memory
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr() as *mut u8
+    }
+
+    /// The memory as a codec state of type `S`.
+    pub(crate) fn as_state<S>(&self) -> NonNull<S> {
+        self.ptr.cast()
+    }
+}
+
+impl Drop for OwnedState {
+    fn drop(&mut self) {
+        let memory = ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.blocks);
+        unsafe { drop(Box::from_raw(memory)) }
+    }
+}
+
+/// State memory borrowed from the caller for `'a`.
+#[derive(Debug)]
+pub struct BorrowedState<'a>(PhantomData<&'a mut [u8]>);
+
+impl<'a> BorrowedState<'a> {
+    /// Start of `size` bytes of state in `buf`, at its first aligned byte.
+    pub(crate) fn new(buf: &'a mut [u8], size: usize) -> Result<(Self, *mut u8), Error> {
+        let offset = buf.as_ptr().align_offset(STATE_ALIGN);
+        match buf.get_mut(offset..) {
+            Some(buf) if buf.len() >= size => Ok((BorrowedState(PhantomData), buf.as_mut_ptr())),
+            _ => Err(Error::BufferTooSmall),
+        }
+    }
+}
+
+/// `size` rounded up to [`STATE_ALIGN`], so states placed one after the
+/// other in a buffer all stay aligned.
+pub(crate) fn padded(size: usize) -> usize {
+    size.div_ceil(STATE_ALIGN) * STATE_ALIGN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::Decoder;
+    use crate::encoder::Encoder;
+    use crate::testing::encode_sine;
+    use crate::types::{Application, Channels, SampleRate};
+
+    /// A zeroed buffer and the offset of its first aligned byte.
+    fn arena(len: usize) -> (Vec<u8>, usize) {
+        let buf = vec![0; len + STATE_ALIGN];
+        let offset = buf.as_ptr().align_offset(STATE_ALIGN);
+        (buf, offset)
+    }
+
+    #[test]
+    fn misaligned_buffer_loses_its_head() {
+        let size = Decoder::state_size(Channels::Stereo);
+        let (mut buf, offset) = arena(size + STATE_ALIGN);
+
+        // Aligning skips the first 15 bytes, leaving too few.
+        let start = offset + 1;
+        let result = Decoder::new_in(
+            &mut buf[start..start + size],
+            SampleRate::Hz48000,
+            Channels::Stereo,
+        );
+        assert_eq!(result.err(), Some(Error::BufferTooSmall));
+
+        let end = start + size + STATE_ALIGN - 1;
+        assert!(
+            Decoder::new_in(&mut buf[start..end], SampleRate::Hz48000, Channels::Stereo).is_ok()
+        );
+    }
+
+    #[test]
+    fn short_buffer_is_rejected() {
+        let size = Encoder::state_size(Channels::Mono);
+        let (mut buf, offset) = arena(size);
+        let short = &mut buf[offset..offset + size - 1];
+        let result = Encoder::new_in(
+            short,
+            SampleRate::Hz48000,
+            Channels::Mono,
+            Application::Audio,
+        );
+        assert_eq!(result.err(), Some(Error::BufferTooSmall));
+        let result = Decoder::new_in(&mut [], SampleRate::Hz48000, Channels::Mono);
+        assert_eq!(result.err(), Some(Error::BufferTooSmall));
+
+        let exact = &mut buf[offset..offset + size];
+        assert!(Encoder::new_in(
+            exact,
+            SampleRate::Hz48000,
+            Channels::Mono,
+            Application::Audio
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn arena_states_are_independent() {
+        let mut encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        let packets = encode_sine(&mut encoder, 3);
+
+        let size = Decoder::state_size(Channels::Stereo);
+        let (mut buf, offset) = arena(3 * size);
+        let mut decoders: Vec<_> = buf[offset..offset + 3 * size]
+            .chunks_exact_mut(size)
+            .map(|chunk| Decoder::new_in(chunk, SampleRate::Hz48000, Channels::Stereo).unwrap())
+            .collect();
+
+        // Decoder `n` only sees the first `n + 1` packets, so a state spilling
+        // into its neighbour would change what the neighbour decodes.
+        let mut pcm = vec![0i16; 960 * 2];
+        for (n, decoder) in decoders.iter_mut().enumerate() {
+            for packet in &packets[..n] {
+                decoder.decode(packet, &mut pcm).unwrap();
+            }
+        }
+        for (n, decoder) in decoders.iter_mut().enumerate() {
+            let mut reference = Decoder::new(SampleRate::Hz48000, Channels::Stereo).unwrap();
+            let mut expected = vec![0i16; 960 * 2];
+            for packet in &packets[..=n] {
+                reference.decode(packet, &mut expected).unwrap();
+            }
+            decoder.decode(&packets[n], &mut pcm).unwrap();
+            assert_eq!(pcm, expected);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::mem;
+
+use crate::encoder::Encoder;
+use crate::error::Error;
+use crate::packet::MAX_FRAME_SIZE;
+use crate::params;
+use crate::sample::Sample;
+use crate::state::OwnedState;
+use crate::types::FrameDuration;
+
+/// Room for 120 ms as six 20 ms frames of the largest size, with code 3
+/// framing: TOC, frame count and five frame lengths. More with
+/// [`Encoder::pad_to`] above that.
+const MAX_PACKET: usize = 6 * MAX_FRAME_SIZE + 2 + 5 * 2;
+
+/// Packet produced by a [`StreamEncoder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamPacket {
+    pub data: Vec<u8>,
+    /// Presentation time of the first sample, in samples per channel at the
+    /// encoder sample rate since the start of the stream.
+    pub timestamp: u64,
+    /// Samples per channel the packet decodes to.
+    pub duration: usize,
+    /// Silence added at the end by [`flush`](StreamEncoder::flush), to be
+    /// trimmed after decoding. 0 for every other packet.
+    pub trailing_padding: usize,
+}
+
+/// Encoder taking PCM in chunks of any length, e.g. from a capture
+/// callback, and emitting a packet for every complete frame.
+///
+/// Incomplete frames are kept until the next [`push`](Self::push). The
+/// timestamps count input samples; decoded audio is delayed by the encoder
+/// lookahead on top ([`ctl::encoder::get_lookahead`](crate::ctl::encoder::get_lookahead)).
+#[derive(Debug)]
+pub struct StreamEncoder<T, M = OwnedState> {
+    encoder: Encoder<M>,
+    frame_duration: FrameDuration,
+    buffer: Vec<T>,
+    timestamp: u64,
+    output: Vec<u8>,
+}
+
+impl<T: Sample, M> StreamEncoder<T, M> {
+    /// Stream of packets of `frame_duration` from `encoder`.
+    pub fn new(encoder: Encoder<M>, frame_duration: FrameDuration) -> Self {
+        StreamEncoder {
+            encoder,
+            frame_duration,
+            buffer: Vec::new(),
+            timestamp: 0,
+            output: vec![0; MAX_PACKET],
+        }
+    }
+
+    pub fn encoder(&self) -> &Encoder<M> {
+        &self.encoder
+    }
+
+    /// The encoder, e.g. to change its settings between packets.
+    pub fn encoder_mut(&mut self) -> &mut Encoder<M> {
+        &mut self.encoder
+    }
+
+    pub fn into_encoder(self) -> Encoder<M> {
+        self.encoder
+    }
+
+    pub fn frame_duration(&self) -> FrameDuration {
+        self.frame_duration
+    }
+
+    /// Samples per channel in a frame.
+    pub fn frame_size(&self) -> usize {
+        self.frame_duration.samples(self.encoder.sample_rate())
+    }
+
+    /// Samples per channel waiting for a complete frame.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() / self.encoder.channels().count()
+    }
+
+    /// Timestamp of the next packet.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Adds interleaved `pcm`, any whole number of samples per channel, and
+    /// appends a packet to `packets` for every frame it completes. Complete
+    /// frames are encoded straight from `pcm`, only the remainder is copied.
+    ///
+    /// On an error the frame that failed is dropped, leaving a gap in the
+    /// timestamps, and the input after it is not used.
+    pub fn push(&mut self, pcm: &[T], packets: &mut Vec<StreamPacket>) -> Result<(), Error> {
+        let channels = self.encoder.channels().count();
+        params::samples_per_channel(pcm.len(), channels)?;
+        let frame_len = self.frame_size() * channels;
+        let mut pcm = pcm;
+
+        if !self.buffer.is_empty() {
+            let missing = frame_len - self.buffer.len();
+            let (head, rest) = pcm.split_at(missing.min(pcm.len()));
+            self.buffer.extend_from_slice(head);
+            pcm = rest;
+            if self.buffer.len() < frame_len {
+                return Ok(());
+            }
+            let buffer = mem::take(&mut self.buffer);
+            let packet = self.encode(&buffer, 0);
+            self.buffer = buffer;
+            self.buffer.clear();
+            packets.push(packet?);
+        }
+        let mut frames = pcm.chunks_exact(frame_len);
+        for frame in &mut frames {
+            packets.push(self.encode(frame, 0)?);
+        }
+        self.buffer.extend_from_slice(frames.remainder());
+        Ok(())
+    }
+
+    /// Encodes the buffered samples as a last frame completed with silence,
+    /// if there are any. The packet reports the padding to trim. The stream
+    /// can go on afterwards, its timestamps then include the padding.
+    pub fn flush(&mut self) -> Result<Option<StreamPacket>, Error> {
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        let frame_len = self.frame_size() * self.encoder.channels().count();
+        let trailing_padding = self.frame_size() - self.buffered();
+        let mut buffer = mem::take(&mut self.buffer);
+        buffer.resize(frame_len, T::default());
+        let packet = self.encode(&buffer, trailing_padding);
+        self.buffer = buffer;
+        self.buffer.clear();
+        packet.map(Some)
+    }
+
+    /// Encodes one complete frame and advances the timestamp past it.
+    fn encode(&mut self, frame: &[T], trailing_padding: usize) -> Result<StreamPacket, Error> {
+        let duration = self.frame_size();
+        let timestamp = self.timestamp;
+        self.timestamp += duration as u64;
+        // The padding size can change between packets through encoder_mut.
+        let size = MAX_PACKET.max(self.encoder.pad_to().unwrap_or(0));
+        if self.output.len() < size {
+            self.output.resize(size, 0);
+        }
+        let len = self.encoder.encode(frame, &mut self.output)?;
+        Ok(StreamPacket {
+            data: self.output[..len].to_vec(),
+            timestamp,
+            duration,
+            trailing_padding,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet;
+    use crate::types::{Application, Channels, SampleRate};
+
+    fn stream() -> StreamEncoder<i16> {
+        let encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+        StreamEncoder::new(encoder, FrameDuration::Ms20)
+    }
+
+    /// `samples` per channel of a stereo ramp.
+    fn input(samples: usize) -> Vec<i16> {
+        (0..samples * 2).map(|i| (i % 2000) as i16).collect()
+    }
+
+    fn push_chunks(
+        stream: &mut StreamEncoder<i16>,
+        chunk: usize,
+        total: usize,
+    ) -> Vec<StreamPacket> {
+        let pcm = input(total);
+        let mut packets = Vec::new();
+        for chunk in pcm.chunks(chunk * 2) {
+            stream.push(chunk, &mut packets).unwrap();
+        }
+        packets
+    }
+
+    #[test]
+    fn chunks_straddling_frames() {
+        for &chunk in &[441, 1024] {
+            let mut stream = stream();
+            let packets = push_chunks(&mut stream, chunk, 10_000);
+            assert_eq!(packets.len(), 10_000 / 960);
+            assert_eq!(stream.buffered(), 10_000 % 960);
+
+            // Same packets as whole frames encoded directly.
+            let mut encoder =
+                Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio).unwrap();
+            let mut output = vec![0; MAX_PACKET];
+            for (packet, frame) in packets.iter().zip(input(10_000).chunks_exact(960 * 2)) {
+                let len = encoder.encode(frame, &mut output).unwrap();
+                assert_eq!(packet.data, output[..len]);
+                assert_eq!(packet.duration, 960);
+                assert_eq!(packet.trailing_padding, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn timestamps_are_monotonic() {
+        let mut stream = stream();
+        let packets = push_chunks(&mut stream, 441, 5000);
+        for (i, packet) in packets.iter().enumerate() {
+            assert_eq!(packet.timestamp, i as u64 * 960);
+        }
+        assert_eq!(stream.timestamp(), packets.len() as u64 * 960);
+    }
+
+    #[test]
+    fn flush_reports_trailing_padding() {
+        let mut stream = stream();
+        let packets = push_chunks(&mut stream, 1024, 2048);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(stream.buffered(), 128);
+
+        let packet = stream.flush().unwrap().unwrap();
+        assert_eq!(packet.timestamp, 1920);
+        assert_eq!(packet.duration, 960);
+        assert_eq!(packet.trailing_padding, 960 - 128);
+        assert_eq!(stream.buffered(), 0);
+        assert_eq!(stream.timestamp(), 2880);
+    }
+
+    #[test]
+    fn flush_of_empty_buffer() {
+        let mut stream = stream();
+        assert_eq!(stream.flush(), Ok(None));
+        push_chunks(&mut stream, 960, 960);
+        assert_eq!(stream.flush(), Ok(None));
+        assert_eq!(stream.timestamp(), 960);
+    }
+
+    #[test]
+    fn padding_beyond_max_packet() {
+        let mut stream = stream();
+        stream.encoder_mut().set_pad_to(Some(MAX_PACKET + 100));
+        let packets = push_chunks(&mut stream, 960, 960);
+        assert_eq!(packets[0].data.len(), MAX_PACKET + 100);
+        assert!(packet::Packet::new(&packets[0].data).is_ok());
+    }
+}
@@ -0,0 +1,14 @@
+//! Raw bindings generated by bindgen.
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+#[cfg(feature = "bindgen")]
+include!(concat!(env!("OUT_DIR"), "/opus_bindings.rs"));
+#[cfg(not(feature = "bindgen"))]
+include!("opus_bindings.rs");
+
+#[cfg(all(feature = "custom-modes", feature = "bindgen"))]
+include!(concat!(env!("OUT_DIR"), "/opus_custom_bindings.rs"));
+#[cfg(all(feature = "custom-modes", not(feature = "bindgen")))]
+include!("opus_custom_bindings.rs");
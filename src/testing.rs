@@ -0,0 +1,28 @@
+//! Fixtures shared by the unit tests.
+
+use crate::encoder::Encoder;
+
+/// Samples per channel of a 20 ms frame at 48 kHz.
+pub(crate) const FRAME: usize = 960;
+
+/// Frame `index` of a 440 Hz sine at 48 kHz, the same on all `channels`.
+pub(crate) fn sine(index: usize, channels: usize) -> Vec<i16> {
+    (0..FRAME * channels)
+        .map(|i| {
+            let t = (index * FRAME + i / channels) as f32 / 48000.0;
+            ((t * 440.0 * std::f32::consts::TAU).sin() * 8000.0) as i16
+        })
+        .collect()
+}
+
+/// `count` packets of consecutive sine frames from `encoder`.
+pub(crate) fn encode_sine(encoder: &mut Encoder, count: usize) -> Vec<Vec<u8>> {
+    let channels = encoder.channels().count();
+    let mut output = [0; 1500];
+    (0..count)
+        .map(|index| {
+            let len = encoder.encode(&sine(index, channels), &mut output).unwrap();
+            output[..len].to_vec()
+        })
+        .collect()
+}
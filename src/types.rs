@@ -0,0 +1,269 @@
+use std::convert::TryFrom;
+use std::os::raw::c_int;
+
+use crate::error::Error;
+use crate::sys::*;
+
+/// Defines a fieldless enum mapped one to one to raw libopus values, with
+/// `raw()`, `From<Self> for c_int` and `TryFrom<c_int>`.
+macro_rules! raw_enum {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident {
+            $($(#[$vattr:meta])* $variant:ident = $raw:expr,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vattr])* $variant,)*
+        }
+
+        impl $name {
+            /// Every value, in increasing raw order.
+            pub const ALL: &'static [$name] = &[$($name::$variant),*];
+
+            /// The raw libopus value.
+            pub fn raw(self) -> c_int {
+                match self {
+                    $($name::$variant => $raw as c_int,)*
+                }
+            }
+        }
+
+        impl From<$name> for c_int {
+            fn from(value: $name) -> c_int {
+                value.raw()
+            }
+        }
+
+        impl TryFrom<c_int> for $name {
+            type Error = Error;
+
+            fn try_from(raw: c_int) -> Result<Self, Error> {
+                $name::ALL
+                    .iter()
+                    .copied()
+                    .find(|value| value.raw() == raw)
+                    .ok_or(Error::BadArg)
+            }
+        }
+    };
+}
+
+raw_enum! {
+    /// Coding mode, `OPUS_APPLICATION_*`.
+    pub enum Application {
+        /// Best quality for voice signals.
+        Voip = OPUS_APPLICATION_VOIP,
+        /// Best quality for most non-voice signals like music.
+        Audio = OPUS_APPLICATION_AUDIO,
+        /// Lowest achievable latency, voice-optimized modes can't be used.
+        RestrictedLowdelay = OPUS_APPLICATION_RESTRICTED_LOWDELAY,
+    }
+}
+
+raw_enum! {
+    /// Audio bandpass, `OPUS_BANDWIDTH_*`.
+    pub enum Bandwidth {
+        /// 4 kHz bandpass.
+        Narrowband = OPUS_BANDWIDTH_NARROWBAND,
+        /// 6 kHz bandpass.
+        Mediumband = OPUS_BANDWIDTH_MEDIUMBAND,
+        /// 8 kHz bandpass.
+        Wideband = OPUS_BANDWIDTH_WIDEBAND,
+        /// 12 kHz bandpass.
+        Superwideband = OPUS_BANDWIDTH_SUPERWIDEBAND,
+        /// 20 kHz bandpass.
+        Fullband = OPUS_BANDWIDTH_FULLBAND,
+    }
+}
+
+raw_enum! {
+    /// Type of signal being encoded, `OPUS_SIGNAL_*`.
+    pub enum Signal {
+        Voice = OPUS_SIGNAL_VOICE,
+        Music = OPUS_SIGNAL_MUSIC,
+    }
+}
+
+raw_enum! {
+    /// Sample rates supported by the encoder and decoder, in Hz.
+    pub enum SampleRate {
+        Hz8000 = 8000,
+        Hz12000 = 12000,
+        Hz16000 = 16000,
+        Hz24000 = 24000,
+        Hz48000 = 48000,
+    }
+}
+
+raw_enum! {
+    /// Channel count of a single-stream encoder or decoder.
+    pub enum Channels {
+        Mono = 1,
+        Stereo = 2,
+    }
+}
+
+raw_enum! {
+    /// Frame duration, `OPUS_FRAMESIZE_*`.
+    pub enum FrameDuration {
+        Ms2_5 = OPUS_FRAMESIZE_2_5_MS,
+        Ms5 = OPUS_FRAMESIZE_5_MS,
+        Ms10 = OPUS_FRAMESIZE_10_MS,
+        Ms20 = OPUS_FRAMESIZE_20_MS,
+        Ms40 = OPUS_FRAMESIZE_40_MS,
+        Ms60 = OPUS_FRAMESIZE_60_MS,
+        Ms80 = OPUS_FRAMESIZE_80_MS,
+        Ms100 = OPUS_FRAMESIZE_100_MS,
+        Ms120 = OPUS_FRAMESIZE_120_MS,
+    }
+}
+
+impl SampleRate {
+    /// The rate in Hz.
+    pub fn hz(self) -> i32 {
+        self.raw()
+    }
+}
+
+impl Channels {
+    pub fn count(self) -> usize {
+        self.raw() as usize
+    }
+}
+
+impl FrameDuration {
+    /// Duration in units of 2.5 ms.
+    fn units(self) -> usize {
+        match self {
+            FrameDuration::Ms2_5 => 1,
+            FrameDuration::Ms5 => 2,
+            FrameDuration::Ms10 => 4,
+            FrameDuration::Ms20 => 8,
+            FrameDuration::Ms40 => 16,
+            FrameDuration::Ms60 => 24,
+            FrameDuration::Ms80 => 32,
+            FrameDuration::Ms100 => 40,
+            FrameDuration::Ms120 => 48,
+        }
+    }
+
+    /// Duration in microseconds.
+    pub fn micros(self) -> u32 {
+        self.units() as u32 * 2500
+    }
+
+    /// Samples per channel of a frame this long at `sample_rate`.
+    pub fn samples(self, sample_rate: SampleRate) -> usize {
+        self.units() * sample_rate.hz() as usize / 400
+    }
+
+    /// The duration of a frame of `samples` per channel at `sample_rate`, if
+    /// that is a valid Opus frame.
+    pub fn from_samples(sample_rate: SampleRate, samples: usize) -> Option<Self> {
+        FrameDuration::ALL
+            .iter()
+            .copied()
+            .find(|duration| duration.samples(sample_rate) == samples)
+    }
+}
+
+/// Encoder bitrate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bitrate {
+    /// `OPUS_AUTO`, picked from the sample rate and channel count.
+    Auto,
+    /// `OPUS_BITRATE_MAX`, as many bits as the output buffer allows.
+    Max,
+    /// Bits per second, 500 to 512000, or the total of all streams for the
+    /// multistream encoders.
+    BitsPerSecond(i32),
+}
+
+impl Bitrate {
+    /// The raw value for `OPUS_SET_BITRATE`.
+    pub fn raw(self) -> c_int {
+        match self {
+            Bitrate::Auto => OPUS_AUTO,
+            Bitrate::Max => OPUS_BITRATE_MAX,
+            Bitrate::BitsPerSecond(bps) => bps,
+        }
+    }
+}
+
+impl From<Bitrate> for c_int {
+    fn from(value: Bitrate) -> c_int {
+        value.raw()
+    }
+}
+
+impl TryFrom<c_int> for Bitrate {
+    type Error = Error;
+
+    fn try_from(raw: c_int) -> Result<Self, Error> {
+        match raw {
+            OPUS_AUTO => Ok(Bitrate::Auto),
+            OPUS_BITRATE_MAX => Ok(Bitrate::Max),
+            bps if bps > 0 => Ok(Bitrate::BitsPerSecond(bps)),
+            _ => Err(Error::BadArg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks every variant of `E` converts to its raw value and back, and
+    /// that `rejected` raw values fail with `Error::BadArg`.
+    fn round_trip<E>(all: &[E], rejected: &[c_int])
+    where
+        E: Copy + PartialEq + std::fmt::Debug + Into<c_int> + TryFrom<c_int, Error = Error>,
+    {
+        for &value in all {
+            assert_eq!(E::try_from(value.into()), Ok(value));
+        }
+        for &raw in rejected {
+            assert_eq!(E::try_from(raw), Err(Error::BadArg), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn raw_enums_round_trip() {
+        round_trip(Application::ALL, &[0, OPUS_AUTO, 2050, 2052]);
+        round_trip(Bandwidth::ALL, &[0, OPUS_AUTO, 1100, 1106]);
+        round_trip(Signal::ALL, &[0, OPUS_AUTO, 3000, 3003]);
+        round_trip(SampleRate::ALL, &[0, 44100, 96000, -48000]);
+        round_trip(Channels::ALL, &[0, 3, 255, -1]);
+        round_trip(FrameDuration::ALL, &[0, OPUS_AUTO, 5000, 5010]);
+        assert_eq!(Application::Voip.raw(), OPUS_APPLICATION_VOIP as c_int);
+        assert_eq!(Bandwidth::Fullband.raw(), OPUS_BANDWIDTH_FULLBAND as c_int);
+        assert_eq!(FrameDuration::Ms120.raw(), OPUS_FRAMESIZE_120_MS as c_int);
+    }
+
+    #[test]
+    fn frame_durations_match_sample_counts() {
+        assert_eq!(FrameDuration::Ms2_5.samples(SampleRate::Hz8000), 20);
+        assert_eq!(FrameDuration::Ms120.samples(SampleRate::Hz48000), 5760);
+        assert_eq!(FrameDuration::Ms20.micros(), 20_000);
+        for &rate in SampleRate::ALL {
+            for &duration in FrameDuration::ALL {
+                let samples = duration.samples(rate);
+                assert_eq!(FrameDuration::from_samples(rate, samples), Some(duration));
+                assert_eq!(FrameDuration::from_samples(rate, samples + 1), None);
+            }
+        }
+    }
+
+    #[test]
+    fn bitrate_round_trip() {
+        for &bitrate in &[Bitrate::Auto, Bitrate::Max, Bitrate::BitsPerSecond(64000)] {
+            assert_eq!(Bitrate::try_from(bitrate.raw()), Ok(bitrate));
+        }
+        assert_eq!(Bitrate::try_from(1), Ok(Bitrate::BitsPerSecond(1)));
+        assert_eq!(Bitrate::try_from(0), Err(Error::BadArg));
+        assert_eq!(Bitrate::try_from(-2), Err(Error::BadArg));
+    }
+}
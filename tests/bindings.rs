@@ -0,0 +1,26 @@
+//! Run with `--features bindgen` to check `src/opus_bindings.rs` against a fresh
+//! bindgen run. On mismatch copy `$OUT_DIR/opus_bindings.rs` over it.
+#![cfg(feature = "bindgen")]
+
+#[test]
+fn checked_in_bindings_are_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/opus_bindings.rs"));
+    let checked_in = include_str!("../src/opus_bindings.rs");
+    assert!(
+        generated == checked_in,
+        "src/opus_bindings.rs is out of date, regenerate it from {}",
+        concat!(env!("OUT_DIR"), "/opus_bindings.rs")
+    );
+}
+
+#[cfg(feature = "custom-modes")]
+#[test]
+fn checked_in_custom_bindings_are_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/opus_custom_bindings.rs"));
+    let checked_in = include_str!("../src/opus_custom_bindings.rs");
+    assert!(
+        generated == checked_in,
+        "src/opus_custom_bindings.rs is out of date, regenerate it from {}",
+        concat!(env!("OUT_DIR"), "/opus_custom_bindings.rs")
+    );
+}